@@ -18,19 +18,6 @@ impl Camera {
         }
     }
 
-    pub fn basis_change(&self, vector: &Vec3) -> Vec3 {
-        let forward = nalgebra_glm::normalize(&(self.center - self.eye));
-        let right = nalgebra_glm::normalize(&nalgebra_glm::cross(&forward, &self.up));
-        let up = nalgebra_glm::cross(&right, &forward);
-
-        let rotated = 
-            right * vector.x +
-            up * vector.y +
-            (-forward) * vector.z;
-
-        rotated.normalize()
-    }
-
     pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
         let radius_vector = self.eye - self.center;
         let radius = radius_vector.magnitude();
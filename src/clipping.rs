@@ -0,0 +1,228 @@
+use nalgebra_glm::{Vec3, Vec4, Mat4};
+use crate::vertex::Vertex;
+
+// Planos del frustum en clip space: un vértice está dentro si dot(plano, posición) >= 0.
+// Con la proyección de nalgebra-glm el volumen visible es -w <= x, y, z <= w.
+const FRUSTUM_PLANES: [[f32; 4]; 6] = [
+    [0.0, 0.0, 1.0, 1.0],  // near:   z >= -w
+    [0.0, 0.0, -1.0, 1.0], // far:    z <= w
+    [1.0, 0.0, 0.0, 1.0],  // left:   x >= -w
+    [-1.0, 0.0, 0.0, 1.0], // right:  x <= w
+    [0.0, 1.0, 0.0, 1.0],  // bottom: y >= -w
+    [0.0, -1.0, 0.0, 1.0], // top:    y <= w
+];
+
+fn plane_distance(plane: &[f32; 4], position: &Vec4) -> f32 {
+    plane[0] * position.x + plane[1] * position.y + plane[2] * position.z + plane[3] * position.w
+}
+
+// Recorta un triángulo contra los seis planos del frustum (Sutherland-Hodgman).
+// Devuelve el polígono convexo resultante, vacío si el triángulo queda fuera.
+pub fn clip_triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Vertex> {
+    let positions = [v1.clip_position, v2.clip_position, v3.clip_position];
+
+    // Caso rápido: todos los vértices dentro de todos los planos
+    let fully_inside = FRUSTUM_PLANES.iter().all(|plane| {
+        positions.iter().all(|p| plane_distance(plane, p) >= 0.0)
+    });
+    if fully_inside {
+        return vec![v1.clone(), v2.clone(), v3.clone()];
+    }
+
+    // Caso rápido: todos los vértices fuera del mismo plano
+    let fully_outside = FRUSTUM_PLANES.iter().any(|plane| {
+        positions.iter().all(|p| plane_distance(plane, p) < 0.0)
+    });
+    if fully_outside {
+        return Vec::new();
+    }
+
+    let mut polygon = vec![v1.clone(), v2.clone(), v3.clone()];
+
+    for plane in &FRUSTUM_PLANES {
+        if polygon.is_empty() {
+            break;
+        }
+
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+
+        for i in 0..polygon.len() {
+            let current = &polygon[i];
+            let next = &polygon[(i + 1) % polygon.len()];
+
+            let d_current = plane_distance(plane, &current.clip_position);
+            let d_next = plane_distance(plane, &next.clip_position);
+
+            if d_current >= 0.0 {
+                clipped.push(current.clone());
+            }
+
+            if (d_current >= 0.0) != (d_next >= 0.0) {
+                let t = d_current / (d_current - d_next);
+                clipped.push(current.lerp(next, t));
+            }
+        }
+
+        polygon = clipped;
+    }
+
+    if polygon.len() < 3 {
+        polygon.clear();
+    }
+
+    polygon
+}
+
+// Recorta un segmento contra los seis planos del frustum. Devuelve los extremos
+// visibles, interpolando los atributos, o None si el segmento queda fuera.
+pub fn clip_line(v1: &Vertex, v2: &Vertex) -> Option<(Vertex, Vertex)> {
+    let (mut t0, mut t1) = (0.0_f32, 1.0_f32);
+
+    for plane in &FRUSTUM_PLANES {
+        let d1 = plane_distance(plane, &v1.clip_position);
+        let d2 = plane_distance(plane, &v2.clip_position);
+
+        if d1 < 0.0 && d2 < 0.0 {
+            return None;
+        }
+        if d1 < 0.0 {
+            t0 = t0.max(d1 / (d1 - d2));
+        } else if d2 < 0.0 {
+            t1 = t1.min(d1 / (d1 - d2));
+        }
+    }
+
+    if t0 > t1 {
+        return None;
+    }
+
+    let start = if t0 > 0.0 { v1.lerp(v2, t0) } else { v1.clone() };
+    let end = if t1 < 1.0 { v1.lerp(v2, t1) } else { v2.clone() };
+    Some((start, end))
+}
+
+// Un vértice está dentro del frustum si está del lado visible de los seis planos
+pub fn is_inside(vertex: &Vertex) -> bool {
+    FRUSTUM_PLANES.iter().all(|plane| plane_distance(plane, &vertex.clip_position) >= 0.0)
+}
+
+// División de perspectiva y transformación de viewport de un vértice ya recortado.
+// Se guarda 1/w para la interpolación con corrección de perspectiva.
+pub fn to_screen(vertex: &mut Vertex, viewport_matrix: &Mat4) {
+    let clip = vertex.clip_position;
+    let inv_w = 1.0 / clip.w;
+    let ndc_position = Vec4::new(clip.x * inv_w, clip.y * inv_w, clip.z * inv_w, 1.0);
+    let screen_position = viewport_matrix * ndc_position;
+
+    vertex.inv_w = inv_w;
+    vertex.transformed_position = Vec3::new(screen_position.x, screen_position.y, screen_position.z);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::Vec2;
+
+    // Vértice en clip space con `value` en las UV y en el primer varying
+    fn clip_vertex(x: f32, y: f32, z: f32, value: f32) -> Vertex {
+        let mut varyings = [0.0; crate::vertex::MAX_VARYINGS];
+        varyings[0] = value;
+        Vertex {
+            clip_position: Vec4::new(x, y, z, 1.0),
+            tex_coords: Vec2::new(value, 1.0 - value),
+            varyings,
+            ..Vertex::default()
+        }
+    }
+
+    fn near_distance(vertex: &Vertex) -> f32 {
+        plane_distance(&FRUSTUM_PLANES[0], &vertex.clip_position)
+    }
+
+    #[test]
+    fn inside_triangle_is_unchanged() {
+        let (a, b, c) = (clip_vertex(0.0, 0.0, 0.0, 0.0), clip_vertex(0.5, 0.0, 0.2, 0.5), clip_vertex(0.0, 0.5, -0.2, 1.0));
+        let polygon = clip_triangle(&a, &b, &c);
+        assert_eq!(polygon.len(), 3);
+        assert_eq!(polygon[1].clip_position, b.clip_position);
+    }
+
+    #[test]
+    fn triangle_behind_near_plane_is_discarded() {
+        let polygon = clip_triangle(
+            &clip_vertex(0.0, 0.0, -2.0, 0.0),
+            &clip_vertex(0.5, 0.0, -3.0, 0.0),
+            &clip_vertex(0.0, 0.5, -1.5, 0.0),
+        );
+        assert!(polygon.is_empty());
+    }
+
+    #[test]
+    fn one_vertex_behind_near_plane_gives_a_quad() {
+        let polygon = clip_triangle(
+            &clip_vertex(0.0, 0.0, -3.0, 0.0),
+            &clip_vertex(0.5, 0.0, 0.0, 1.0),
+            &clip_vertex(0.0, 0.5, 0.0, 1.0),
+        );
+        assert_eq!(polygon.len(), 4);
+        assert!(polygon.iter().all(|v| near_distance(v) >= -1e-6));
+        // Dos vértices nuevos sobre el plano cercano
+        assert_eq!(polygon.iter().filter(|v| near_distance(v).abs() < 1e-6).count(), 2);
+    }
+
+    #[test]
+    fn two_vertices_behind_near_plane_give_a_triangle() {
+        let polygon = clip_triangle(
+            &clip_vertex(0.0, 0.0, 0.0, 1.0),
+            &clip_vertex(0.5, 0.0, -3.0, 0.0),
+            &clip_vertex(0.0, 0.5, -3.0, 0.0),
+        );
+        assert_eq!(polygon.len(), 3);
+        assert!(polygon.iter().all(|v| near_distance(v) >= -1e-6));
+    }
+
+    #[test]
+    fn new_vertices_interpolate_attributes() {
+        // La arista de z = -3 a z = 0 cruza el plano cercano (z = -1) en t = 2/3
+        let polygon = clip_triangle(
+            &clip_vertex(0.0, 0.0, -3.0, 0.0),
+            &clip_vertex(0.6, 0.0, 0.0, 1.0),
+            &clip_vertex(0.0, 0.6, 0.0, 1.0),
+        );
+
+        let expected = 2.0 / 3.0;
+        let on_plane: Vec<&Vertex> = polygon.iter().filter(|v| near_distance(v).abs() < 1e-6).collect();
+        assert_eq!(on_plane.len(), 2);
+        for vertex in on_plane {
+            assert!((vertex.varyings[0] - expected).abs() < 1e-5);
+            assert!((vertex.tex_coords.x - expected).abs() < 1e-5);
+            assert!((vertex.tex_coords.y - (1.0 - expected)).abs() < 1e-5);
+            assert!((vertex.clip_position.x + vertex.clip_position.y - 0.6 * expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn line_is_clipped_at_the_near_plane() {
+        // De z = -3 a z = 0 el segmento entra por el plano cercano en t = 2/3
+        let (start, end) = clip_line(&clip_vertex(0.0, 0.0, -3.0, 0.0), &clip_vertex(0.3, 0.0, 0.0, 1.0)).unwrap();
+        assert!(near_distance(&start).abs() < 1e-6);
+        assert!((start.varyings[0] - 2.0 / 3.0).abs() < 1e-5);
+        assert_eq!(end.clip_position, Vec4::new(0.3, 0.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn line_outside_frustum_is_discarded() {
+        assert!(clip_line(&clip_vertex(0.0, 0.0, -2.0, 0.0), &clip_vertex(0.5, 0.0, -3.0, 0.0)).is_none());
+        // Cruza por fuera de una esquina: cada extremo está dentro de un plano distinto
+        assert!(clip_line(&clip_vertex(-0.5, 1.5, 0.0, 0.0), &clip_vertex(1.5, -0.5, 0.0, 0.0)).is_some());
+        assert!(clip_line(&clip_vertex(0.5, 2.0, 0.0, 0.0), &clip_vertex(2.0, 0.5, 0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn to_screen_divides_by_w() {
+        let mut vertex = Vertex { clip_position: Vec4::new(1.0, -1.0, 0.5, 2.0), ..Vertex::default() };
+        to_screen(&mut vertex, &Mat4::identity());
+        assert_eq!(vertex.inv_w, 0.5);
+        assert_eq!(vertex.transformed_position, Vec3::new(0.5, -0.5, 0.25));
+    }
+}
@@ -1,55 +1,90 @@
 use std::fmt;
+use nalgebra_glm::{Vec3, Vec4};
 use std::ops::{Add, Mul};
 
-#[derive(Debug, Clone, Copy)]
+// Color sRGB de 8 bits por canal. `a` es la opacidad (255 = opaco) y no está
+// premultiplicada; solo la usan los shaders con un modo de mezcla transparente.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
     pub b: u8,
+    pub a: u8,
 }
 
 impl Color {
     pub fn new(r: u8, g: u8, b: u8) -> Self {
-        Color { r, g, b }
+        Color { r, g, b, a: 255 }
+    }
+
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Color { r, g, b, a }
     }
 
     pub fn black() -> Self {
-        Color { r: 0, g: 0, b: 0 }
+        Color::new(0, 0, 0)
     }
 
     pub fn white() -> Self {
-        Color { r: 255, g: 255, b: 255 }
+        Color::new(255, 255, 255)
     }
 
-    pub fn from_float(r: f32, g: f32, b: f32) -> Self {
-        Color {
-            r: (r.clamp(0.0, 1.0) * 255.0) as u8,
-            g: (g.clamp(0.0, 1.0) * 255.0) as u8,
-            b: (b.clamp(0.0, 1.0) * 255.0) as u8,
-        }
+    pub fn with_alpha(mut self, alpha: u8) -> Self {
+        self.a = alpha;
+        self
     }
 
-    pub fn from_hex(hex: u32) -> Self {
-        let r = ((hex >> 16) & 0xFF) as u8;
-        let g = ((hex >> 8) & 0xFF) as u8;
-        let b = (hex & 0xFF) as u8;
-        Color { r, g, b }
+    // Opacidad en [0, 1]
+    pub fn alpha(&self) -> f32 {
+        self.a as f32 / 255.0
     }
 
-    pub fn to_hex(&self) -> u32 {
+    pub fn from_float(r: f32, g: f32, b: f32) -> Self {
+        Color::new(
+            (r.clamp(0.0, 1.0) * 255.0) as u8,
+            (g.clamp(0.0, 1.0) * 255.0) as u8,
+            (b.clamp(0.0, 1.0) * 255.0) as u8,
+        )
+    }
+
+    // Solo RGB: el buffer de presentación no tiene canal alfa
+    pub fn to_hex(self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
 
-    pub fn lerp(&self, other: &Color, t: f32) -> Color {
-        let t = t.clamp(0.0, 1.0);
-        Color::new(
-            (self.r as f32 + (other.r as f32 - self.r as f32) * t) as u8,
-            (self.g as f32 + (other.g as f32 - self.g as f32) * t) as u8,
-            (self.b as f32 + (other.b as f32 - self.b as f32) * t) as u8,
-        )
+    // Decodifica sRGB de 8 bits a valores lineales en [0, 1], el espacio en el
+    // que la luz se suma correctamente
+    pub fn to_linear(self) -> Vec3 {
+        let channel = |c: u8| {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+        };
+        Vec3::new(channel(self.r), channel(self.g), channel(self.b))
+    }
+
+    // Codifica un color lineal a sRGB de 8 bits; los valores fuera de [0, 1] se recortan
+    pub fn from_linear(color: Vec3) -> Color {
+        let channel = |c: f32| {
+            let c = c.clamp(0.0, 1.0);
+            let encoded = if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 };
+            (encoded * 255.0).round().clamp(0.0, 255.0) as u8
+        };
+        Color::new(channel(color.x), channel(color.y), channel(color.z))
+    }
+
+    // Color lineal con la opacidad en `w`, como lo devuelve `FragmentShader::shade_hdr`
+    pub fn to_linear_rgba(self) -> Vec4 {
+        let linear = self.to_linear();
+        Vec4::new(linear.x, linear.y, linear.z, self.alpha())
+    }
+
+    pub fn from_linear_rgba(color: Vec4) -> Color {
+        let alpha = (color.w.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Color::from_linear(color.xyz()).with_alpha(alpha)
     }
 }
 
+// La suma y los productos operan sobre RGB y conservan la opacidad de `self`
 impl Add for Color {
     type Output = Color;
 
@@ -58,6 +93,7 @@ impl Add for Color {
             r: self.r.saturating_add(other.r),
             g: self.g.saturating_add(other.g),
             b: self.b.saturating_add(other.b),
+            a: self.a,
         }
     }
 }
@@ -70,12 +106,57 @@ impl Mul<f32> for Color {
             r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
             g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
             b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
+            a: self.a,
+        }
+    }
+}
+
+// Multiplicación por canal, p. ej. por la radiancia de una luz de color
+impl Mul<Vec3> for Color {
+    type Output = Color;
+
+    fn mul(self, factor: Vec3) -> Color {
+        Color {
+            r: (self.r as f32 * factor.x).clamp(0.0, 255.0) as u8,
+            g: (self.g as f32 * factor.y).clamp(0.0, 255.0) as u8,
+            b: (self.b as f32 * factor.z).clamp(0.0, 255.0) as u8,
+            a: self.a,
         }
     }
 }
 
 impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Color({}, {}, {})", self.r, self.g, self.b)
+        if self.a == 255 {
+            write!(f, "Color({}, {}, {})", self.r, self.g, self.b)
+        } else {
+            write!(f, "Color({}, {}, {}, {})", self.r, self.g, self.b, self.a)
+        }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn srgb_round_trip_keeps_every_byte() {
+        for value in 0..=255u8 {
+            let color = Color::new(value, 255 - value, value / 2);
+            let round_trip = Color::from_linear(color.to_linear());
+            assert_eq!(round_trip.to_hex(), color.to_hex(), "valor {}", value);
+        }
+    }
+
+    #[test]
+    fn srgb_curve_reference_values() {
+        let linear = Color::new(0, 128, 255).to_linear();
+        assert_eq!(linear.x, 0.0);
+        assert!((linear.y - 0.2158605).abs() < 1e-5);
+        assert_eq!(linear.z, 1.0);
+        // El gris medio lineal se codifica como 188, no como 128
+        assert_eq!(Color::from_linear(Vec3::repeat(0.5)).r, 188);
+        // Fuera de rango se recorta
+        assert_eq!(Color::from_linear(Vec3::new(-1.0, 2.0, 0.0)).to_hex(), 0x00FF00);
+    }
+}
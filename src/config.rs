@@ -0,0 +1,298 @@
+use crate::export::ImageFormat;
+use crate::framebuffer::{is_valid_sample_count, SCREEN_WIDTH, SCREEN_HEIGHT};
+use crate::pipeline::{CullMode, FrontFace, PipelineState, RenderMode, TransparencyMode};
+use crate::shadow::{ShadowSettings, MAX_PCF_RADIUS};
+use crate::texture::{FilterMode, WrapMode, MAX_ANISOTROPY};
+use crate::tonemap::ToneMapping;
+
+pub struct Config {
+    pub headless: bool,
+    pub model_path: String,
+    pub scene_path: Option<String>,
+    pub shader: String,
+    pub width: usize,
+    pub height: usize,
+    pub frames: usize,
+    pub orbit_step: f32,
+    pub output_dir: String,
+    pub image_format: ImageFormat,
+    pub pipeline: PipelineState,
+    pub threads: Option<usize>,
+    pub msaa_samples: usize,
+    pub lights: Option<Vec<String>>,
+    pub texture_path: Option<String>,
+    pub normal_map_path: Option<String>,
+    // Mapas opcionales del shader pbr
+    pub pbr_albedo_path: Option<String>,
+    pub pbr_metallic_roughness_path: Option<String>,
+    pub pbr_ao_path: Option<String>,
+    pub texture_filter: FilterMode,
+    pub texture_wrap: WrapMode,
+    pub anisotropy: u32,
+    // Cubemap del fondo y de los reflejos; None usa el cielo estrellado
+    pub skybox_path: Option<String>,
+    pub skybox: bool,
+    pub shadows: bool,
+    pub shadow_settings: ShadowSettings,
+    pub tone_mapping: ToneMapping,
+    pub exposure: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            headless: false,
+            model_path: String::from("spaceship.obj"),
+            scene_path: None,
+            shader: String::from("diffuse"),
+            width: SCREEN_WIDTH,
+            height: SCREEN_HEIGHT,
+            frames: 1,
+            orbit_step: 0.0,
+            output_dir: String::from("renders"),
+            image_format: ImageFormat::Png,
+            pipeline: PipelineState::default(),
+            threads: None,
+            msaa_samples: 1,
+            lights: None,
+            texture_path: None,
+            normal_map_path: None,
+            pbr_albedo_path: None,
+            pbr_metallic_roughness_path: None,
+            pbr_ao_path: None,
+            texture_filter: FilterMode::Trilinear,
+            texture_wrap: WrapMode::Repeat,
+            anisotropy: 1,
+            skybox_path: None,
+            skybox: true,
+            shadows: true,
+            shadow_settings: ShadowSettings::default(),
+            tone_mapping: ToneMapping::None,
+            exposure: 1.0,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args<I: Iterator<Item = String>>(mut args: I) -> Result<Self, String> {
+        let mut config = Config::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => config.headless = true,
+                "--model" => config.model_path = next_value(&mut args, &arg)?,
+                "--scene" => config.scene_path = Some(next_value(&mut args, &arg)?),
+                "--shader" => config.shader = next_value(&mut args, &arg)?,
+                "--output" => config.output_dir = next_value(&mut args, &arg)?,
+                "--format" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.image_format = ImageFormat::from_name(&value)
+                        .ok_or_else(|| format!("Formato de imagen desconocido: {}", value))?;
+                }
+                "--cull" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.pipeline.cull_mode = CullMode::from_name(&value)
+                        .ok_or_else(|| format!("Modo de culling desconocido: {}", value))?;
+                }
+                "--winding" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.pipeline.front_face = FrontFace::from_name(&value)
+                        .ok_or_else(|| format!("Orden de vértices desconocido: {}", value))?;
+                }
+                "--msaa" => {
+                    let samples = parse_value(&next_value(&mut args, &arg)?, &arg)?;
+                    if !is_valid_sample_count(samples) {
+                        return Err(format!("MSAA inválido: {} (usa 1, 2, 4 u 8)", samples));
+                    }
+                    config.msaa_samples = samples;
+                }
+                "--threads" => {
+                    config.threads = Some(parse_value(&next_value(&mut args, &arg)?, &arg)?);
+                }
+                "--mode" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.pipeline.render_mode = RenderMode::from_name(&value)
+                        .ok_or_else(|| format!("Modo de render desconocido: {}", value))?;
+                }
+                "--texture" => config.texture_path = Some(next_value(&mut args, &arg)?),
+                "--normal-map" => config.normal_map_path = Some(next_value(&mut args, &arg)?),
+                "--pbr-albedo" => config.pbr_albedo_path = Some(next_value(&mut args, &arg)?),
+                "--pbr-metal-rough" => config.pbr_metallic_roughness_path = Some(next_value(&mut args, &arg)?),
+                "--pbr-ao" => config.pbr_ao_path = Some(next_value(&mut args, &arg)?),
+                "--filter" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.texture_filter = FilterMode::from_name(&value)
+                        .ok_or_else(|| format!("Filtro de textura desconocido: {}", value))?;
+                }
+                "--wrap" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.texture_wrap = WrapMode::from_name(&value)
+                        .ok_or_else(|| format!("Modo de repetición desconocido: {}", value))?;
+                }
+                "--anisotropy" => {
+                    let value: u32 = parse_value(&next_value(&mut args, &arg)?, &arg)?;
+                    if value == 0 || value > MAX_ANISOTROPY {
+                        return Err(format!("Anisotropía inválida: {} (usa de 1 a {})", value, MAX_ANISOTROPY));
+                    }
+                    config.anisotropy = value;
+                }
+                "--skybox" => config.skybox_path = Some(next_value(&mut args, &arg)?),
+                "--no-skybox" => config.skybox = false,
+                "--no-shadows" => config.shadows = false,
+                "--shadow-size" => {
+                    let size: usize = parse_value(&next_value(&mut args, &arg)?, &arg)?;
+                    if size == 0 {
+                        return Err(String::from("El tamaño del shadow map debe ser mayor que cero"));
+                    }
+                    config.shadow_settings.size = size;
+                }
+                "--pcf" => {
+                    let radius: usize = parse_value(&next_value(&mut args, &arg)?, &arg)?;
+                    if radius > MAX_PCF_RADIUS {
+                        return Err(format!("Radio PCF inválido: {} (usa de 0 a {})", radius, MAX_PCF_RADIUS));
+                    }
+                    config.shadow_settings.pcf_radius = radius;
+                }
+                "--tonemap" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.tone_mapping = ToneMapping::from_name(&value)
+                        .ok_or_else(|| format!("Tone mapping desconocido: {}", value))?;
+                }
+                "--exposure" => {
+                    let value: f32 = parse_value(&next_value(&mut args, &arg)?, &arg)?;
+                    if !(value > 0.0 && value.is_finite()) {
+                        return Err(format!("Exposición inválida: {} (debe ser mayor que cero)", value));
+                    }
+                    config.exposure = value;
+                }
+                "--transparency" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.pipeline.transparency = TransparencyMode::from_name(&value)
+                        .ok_or_else(|| format!("Modo de transparencia desconocido: {}", value))?;
+                }
+                "--line-aa" => config.pipeline.line_antialiasing = true,
+                "--lights" => {
+                    let value = next_value(&mut args, &arg)?;
+                    config.lights = Some(value.split(',').map(|name| name.trim().to_string()).collect());
+                }
+                "--frames" => {
+                    config.frames = parse_value(&next_value(&mut args, &arg)?, &arg)?;
+                }
+                "--orbit" => {
+                    config.orbit_step = parse_value(&next_value(&mut args, &arg)?, &arg)?;
+                }
+                "--size" => {
+                    let value = next_value(&mut args, &arg)?;
+                    let (w, h) = value
+                        .split_once('x')
+                        .ok_or_else(|| format!("Tamaño inválido '{}', se esperaba ANCHOxALTO", value))?;
+                    config.width = parse_value(w, &arg)?;
+                    config.height = parse_value(h, &arg)?;
+                }
+                "--help" | "-h" => return Err(usage()),
+                _ => return Err(format!("Argumento desconocido: {}\n\n{}", arg, usage())),
+            }
+        }
+
+        if config.width == 0 || config.height == 0 {
+            return Err(String::from("El tamaño del framebuffer debe ser mayor que cero"));
+        }
+
+        Ok(config)
+    }
+}
+
+fn next_value<I: Iterator<Item = String>>(args: &mut I, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("Falta el valor para {}", flag))
+}
+
+fn parse_value<T: std::str::FromStr>(value: &str, flag: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Valor inválido para {}: {}", flag, value))
+}
+
+fn usage() -> String {
+    String::from(
+        "Uso: proyecto2 [opciones]\n\
+         \n\
+         Opciones:\n\
+         \x20 --headless          Renderiza sin ventana y guarda los frames en disco\n\
+         \x20 --model <ruta>      Archivo OBJ a cargar (por defecto spaceship.obj)\n\
+         \x20 --scene <ruta>      Archivo de escena con varios modelos (reemplaza a --model)\n\
+         \x20 --shader <nombre>   static_color, diffuse, cel_shading, procedural, normal_map,\n\
+         \x20                     blinn_phong, phong, pbr, textured, normal_mapped,\n\
+         \x20                     reflective, refractive\n\
+         \x20 --texture <ruta>    Imagen (PNG, JPEG, BMP o TGA) para el shader textured\n\
+         \x20 --normal-map <ruta> Normal map en espacio tangente para el shader normal_mapped\n\
+         \x20 --pbr-albedo <ruta> Albedo (color base) del shader pbr\n\
+         \x20 --pbr-metal-rough <ruta>\n\
+         \x20                     Rugosidad en G y metalicidad en B del shader pbr (glTF)\n\
+         \x20 --pbr-ao <ruta>     Oclusión ambiental en R del shader pbr\n\
+         \x20 --filter <modo>     Filtro de textura: nearest, bilinear o trilinear (por defecto trilinear)\n\
+         \x20 --anisotropy <n>    Muestras del filtro anisotrópico, de 1 a 16 (por defecto 1)\n\
+         \x20 --wrap <modo>       UV fuera de [0, 1]: repeat, clamp o mirror (por defecto repeat)\n\
+         \x20 --skybox <ruta>     Carpeta con px, nx, py, ny, pz, nz o panorama equirectangular\n\
+         \x20                     (por defecto un cielo estrellado)\n\
+         \x20 --no-skybox        Fondo de color liso; los reflejos siguen usando el cubemap\n\
+         \x20 --no-shadows        Desactiva las sombras de las luces direccionales y spot\n\
+         \x20 --shadow-size <n>   Resolución de los shadow maps (por defecto 1024)\n\
+         \x20 --pcf <radio>       Radio del filtro PCF en texels, de 0 a 4 (por defecto 1 = 3x3)\n\
+         \x20 --tonemap <modo>    Tone mapping: none, reinhard, aces o exposure (por defecto none)\n\
+         \x20 --exposure <x>      Multiplicador de la radiancia antes del tone mapping (por defecto 1)\n\
+         \x20 --mode <modo>       solid, wireframe, points, solid-wireframe o hidden-line\n\
+         \x20 --line-aa           Líneas con anti-aliasing (Xiaolin Wu)\n\
+         \x20 --transparency <m>  sorted (ordenada por objeto y triángulo) u oit (A-buffer)\n\
+         \x20 --lights <lista>    Luces encendidas del rig: key, fill, rim (por defecto key)\n\
+         \x20 --cull <modo>       Face culling: back, front o none (por defecto back)\n\
+         \x20 --winding <orden>  Cara frontal: ccw o cw (por defecto ccw)\n\
+         \x20 --msaa <n>          Muestras de anti-aliasing por píxel: 1, 2, 4 u 8 (por defecto 1)\n\
+         \x20 --threads <n>       Hilos del rasterizador (por defecto todos los núcleos)\n\
+         \x20 --size <AxH>        Resolución del framebuffer (por defecto 800x600)\n\
+         \x20 --frames <n>        Número de frames a renderizar en modo headless\n\
+         \x20 --orbit <rad>       Órbita horizontal de la cámara por frame (modo headless)\n\
+         \x20 --output <dir>      Carpeta de salida para frames y capturas (por defecto renders)\n\
+         \x20 --format <fmt>      Formato de imagen: png, ppm o tga (por defecto png)",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Config, String> {
+        Config::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_give_the_defaults() {
+        let config = parse(&[]).unwrap();
+        assert!(!config.headless);
+        assert_eq!(config.model_path, "spaceship.obj");
+        assert_eq!((config.width, config.height), (SCREEN_WIDTH, SCREEN_HEIGHT));
+        assert_eq!(config.frames, 1);
+    }
+
+    #[test]
+    fn parses_headless_options() {
+        let config = parse(&[
+            "--headless", "--model", "nave.obj", "--size", "320x240", "--frames", "12", "--orbit", "0.25", "--output", "salida",
+        ])
+        .unwrap();
+        assert!(config.headless);
+        assert_eq!(config.model_path, "nave.obj");
+        assert_eq!((config.width, config.height), (320, 240));
+        assert_eq!(config.frames, 12);
+        assert_eq!(config.orbit_step, 0.25);
+        assert_eq!(config.output_dir, "salida");
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        assert!(matches!(parse(&["--frames"]), Err(e) if e.contains("--frames")));
+        assert!(parse(&["--frames", "muchos"]).is_err());
+        assert!(parse(&["--size", "320"]).is_err());
+        assert!(parse(&["--size", "0x240"]).is_err());
+        assert!(matches!(parse(&["--desconocido"]), Err(e) if e.contains("--desconocido")));
+    }
+}
@@ -0,0 +1,270 @@
+use nalgebra_glm::{Vec2, Vec3};
+use rayon::prelude::*;
+use std::f32::consts::PI;
+use std::path::Path;
+use crate::color::Color;
+use crate::texture::{FilterMode, Texture, WrapMode};
+
+// Orden de las caras, el mismo de OpenGL: +X, -X, +Y, -Y, +Z, -Z
+pub const FACE_NAMES: [&str; 6] = ["px", "nx", "py", "ny", "pz", "nz"];
+const FACE_EXTENSIONS: [&str; 5] = ["png", "jpg", "jpeg", "bmp", "tga"];
+
+// Mapa de entorno de seis caras cuadradas que se muestrea con una dirección.
+// Sirve de fondo (skybox) y de entorno para los shaders de reflexión y refracción.
+pub struct CubeMap {
+    pub face_size: usize,
+    pub faces: Vec<Texture>,
+}
+
+impl CubeMap {
+    // Las caras deben ser cuadradas y del mismo tamaño, en el orden de FACE_NAMES
+    pub fn new(faces: Vec<Texture>) -> Result<Self, String> {
+        if faces.len() != 6 {
+            return Err(format!("Un cubemap necesita 6 caras, se recibieron {}", faces.len()));
+        }
+        let face_size = faces[0].width;
+        for (face, name) in faces.iter().zip(FACE_NAMES) {
+            if face.width != face_size || face.height != face_size {
+                return Err(format!(
+                    "La cara {} mide {}x{}; todas deben medir {}x{}",
+                    name, face.width, face.height, face_size, face_size
+                ));
+            }
+        }
+
+        // Sin mipmaps ni repetición: el borde de cada cara continúa en la vecina
+        let faces = faces
+            .into_iter()
+            .map(|face| face.with_filter(FilterMode::Bilinear).with_wrap(WrapMode::ClampToEdge))
+            .collect();
+        Ok(CubeMap { face_size, faces })
+    }
+
+    // Genera cada cara evaluando `color` en la dirección del centro de cada texel
+    pub fn from_fn<F>(face_size: usize, color: F) -> Self
+    where
+        F: Fn(usize, usize, usize, Vec3) -> Color + Sync,
+    {
+        let faces = (0..6)
+            .map(|face| {
+                let data = (0..face_size * face_size)
+                    .into_par_iter()
+                    .map(|i| {
+                        let (x, y) = (i % face_size, i / face_size);
+                        let s = (x as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                        let t = (y as f32 + 0.5) / face_size as f32 * 2.0 - 1.0;
+                        color(face, x, y, face_direction(face, s, t))
+                    })
+                    .collect();
+                Texture::new(face_size, face_size, data)
+            })
+            .collect();
+
+        CubeMap::new(faces).expect("las caras generadas son cuadradas")
+    }
+
+    // Seis imágenes, una por cara, en el orden de FACE_NAMES
+    pub fn load_faces(paths: &[String]) -> Result<Self, String> {
+        let faces = paths
+            .iter()
+            .map(|path| Texture::load(path))
+            .collect::<Result<Vec<_>, _>>()?;
+        CubeMap::new(faces)
+    }
+
+    // Carpeta con px, nx, py, ny, pz y nz en cualquier formato soportado
+    pub fn load_dir(dir: &str) -> Result<Self, String> {
+        let paths = FACE_NAMES
+            .iter()
+            .map(|name| {
+                FACE_EXTENSIONS
+                    .iter()
+                    .map(|extension| Path::new(dir).join(format!("{}.{}", name, extension)))
+                    .find(|path| path.is_file())
+                    .map(|path| path.to_string_lossy().into_owned())
+                    .ok_or_else(|| format!("Falta la cara '{}' del cubemap en '{}'", name, dir))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        CubeMap::load_faces(&paths)
+    }
+
+    // Convierte un panorama equirectangular (360° x 180°) en un cubemap
+    pub fn from_equirectangular(panorama: Texture, face_size: usize) -> Self {
+        let mut panorama = panorama.with_filter(FilterMode::Bilinear);
+        panorama.wrap_u = WrapMode::Repeat;
+        panorama.wrap_v = WrapMode::ClampToEdge;
+
+        CubeMap::from_fn(face_size, |_, _, _, direction| {
+            let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * PI);
+            let v = 0.5 + direction.y.clamp(-1.0, 1.0).asin() / PI;
+            panorama.sample(Vec2::new(u, v))
+        })
+    }
+
+    // Una carpeta se lee como seis caras y una imagen como panorama equirectangular
+    pub fn load(path: &str) -> Result<Self, String> {
+        if Path::new(path).is_dir() {
+            return CubeMap::load_dir(path);
+        }
+        let panorama = Texture::load(path)?;
+        // Cada cara cubre 90° de los 360° del panorama
+        let face_size = (panorama.width / 4).max(1);
+        Ok(CubeMap::from_equirectangular(panorama, face_size))
+    }
+
+    // Cielo estrellado con una nebulosa tenue. Las estrellas dependen solo de
+    // `seed`, así que el fondo es siempre el mismo.
+    pub fn starfield(face_size: usize, seed: u32) -> Self {
+        CubeMap::from_fn(face_size, |face, x, y, direction| {
+            let nebula = nebula(&direction);
+            let mut color = Vec3::new(4.0, 4.0, 12.0) + Vec3::new(60.0, 25.0, 90.0) * nebula.x
+                + Vec3::new(20.0, 50.0, 90.0) * nebula.y;
+
+            // La densidad se escala con el área del texel para que el cielo se
+            // vea igual con cualquier resolución
+            let cell = hash(seed, face as u32, x as u32, y as u32);
+            let density = 0.004 * (512.0 / face_size as f32).powi(2);
+            if cell < density {
+                let brightness = hash(seed ^ 0x9e37_79b9, face as u32, x as u32, y as u32);
+                let temperature = hash(seed ^ 0x85eb_ca6b, face as u32, x as u32, y as u32);
+                // De estrellas rojizas a azuladas
+                let tint = Vec3::new(1.0, 0.85, 0.7).lerp(&Vec3::new(0.7, 0.8, 1.0), temperature);
+                color += tint * (80.0 + 175.0 * brightness.powi(3));
+            }
+
+            let channel = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+            Color::new(channel(color.x), channel(color.y), channel(color.z))
+        })
+    }
+
+    // Color del entorno en la dirección `direction` (no hace falta normalizarla)
+    pub fn sample(&self, direction: &Vec3) -> Color {
+        let (face, uv) = face_uv(direction);
+        self.faces[face].sample(uv)
+    }
+}
+
+impl Default for CubeMap {
+    fn default() -> Self {
+        CubeMap::starfield(256, 1)
+    }
+}
+
+// Cara y UV de una dirección. El eje de mayor magnitud elige la cara y los
+// otros dos, divididos por él, dan la posición (s, t) con t hacia abajo en la
+// imagen, según la tabla de OpenGL. v = 1 - t porque v = 0 es la fila inferior.
+fn face_uv(direction: &Vec3) -> (usize, Vec2) {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+    let (face, s, t, major) = if ax >= ay && ax >= az {
+        if x > 0.0 { (0, -z, -y, ax) } else { (1, z, -y, ax) }
+    } else if ay >= az {
+        if y > 0.0 { (2, x, z, ay) } else { (3, x, -z, ay) }
+    } else if z > 0.0 {
+        (4, x, -y, az)
+    } else {
+        (5, -x, -y, az)
+    };
+
+    if major <= 0.0 {
+        return (4, Vec2::new(0.5, 0.5));
+    }
+
+    let s = (s / major + 1.0) * 0.5;
+    let t = (t / major + 1.0) * 0.5;
+    (face, Vec2::new(s, 1.0 - t))
+}
+
+// Inversa de `face_uv`: dirección de la posición (s, t) en [-1, 1] de una cara
+fn face_direction(face: usize, s: f32, t: f32) -> Vec3 {
+    let direction = match face {
+        0 => Vec3::new(1.0, -t, -s),
+        1 => Vec3::new(-1.0, -t, s),
+        2 => Vec3::new(s, 1.0, t),
+        3 => Vec3::new(s, -1.0, -t),
+        4 => Vec3::new(s, -t, 1.0),
+        _ => Vec3::new(-s, -t, -1.0),
+    };
+    direction.normalize()
+}
+
+// Dos capas de nubes suaves en [0, 1], continuas entre caras porque solo
+// dependen de la dirección
+fn nebula(direction: &Vec3) -> Vec2 {
+    let d = direction * 3.0;
+    let wave = |a: f32, b: f32, c: f32| (a.sin() * b.cos() + (b * 1.7 + c).sin() * 0.5 + (c * 2.3 - a).cos() * 0.25) / 1.75;
+
+    let first = wave(d.x + 0.3, d.y * 1.3, d.z - 0.7);
+    let second = wave(d.z * 1.1 - 1.2, d.x + 0.5, d.y * 0.9 + 2.0);
+
+    // Solo las crestas se ven, el resto queda en negro
+    let shape = |v: f32| ((v - 0.35) / 0.65).clamp(0.0, 1.0).powi(2);
+    Vec2::new(shape(first), shape(second))
+}
+
+// Número pseudoaleatorio en [0, 1) a partir de un texel
+fn hash(seed: u32, face: u32, x: u32, y: u32) -> f32 {
+    let mut h = seed
+        .wrapping_mul(0x27d4_eb2d)
+        .wrapping_add(face.wrapping_mul(0x1656_67b1))
+        .wrapping_add(x.wrapping_mul(0x9e37_79b1))
+        .wrapping_add(y.wrapping_mul(0x85eb_ca77));
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^= h >> 12;
+    h = h.wrapping_mul(0x297a_2d39);
+    h ^= h >> 15;
+    (h >> 8) as f32 / (1u32 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn axis_directions_hit_the_center_of_their_face() {
+        let axes = [
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(-1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+            Vec3::new(0.0, -1.0, 0.0),
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(0.0, 0.0, -1.0),
+        ];
+        for (face, axis) in axes.iter().enumerate() {
+            assert_eq!(face_uv(&(axis * 3.0)), (face, Vec2::new(0.5, 0.5)));
+        }
+    }
+
+    #[test]
+    fn face_uv_inverts_face_direction() {
+        for face in 0..6 {
+            for (s, t) in [(-0.75, 0.5), (0.25, -0.9), (0.6, 0.6)] {
+                let (found, uv) = face_uv(&face_direction(face, s, t));
+                assert_eq!(found, face);
+                assert!((uv.x - (s + 1.0) * 0.5).abs() < 1e-5);
+                assert!((uv.y - (1.0 - (t + 1.0) * 0.5)).abs() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn sample_reads_the_selected_face() {
+        let cubemap = CubeMap::from_fn(4, |face, _, _, _| Color::new(face as u8 * 40, 0, 0));
+        assert_eq!(cubemap.sample(&Vec3::new(-2.0, 0.5, 0.3)).r, 40);
+        assert_eq!(cubemap.sample(&Vec3::new(0.1, -0.2, -5.0)).r, 200);
+        // Dirección nula: se usa el centro de +Z en vez de dividir por cero
+        assert_eq!(face_uv(&Vec3::zeros()), (4, Vec2::new(0.5, 0.5)));
+    }
+
+    #[test]
+    fn new_rejects_wrong_faces() {
+        let face = |size: usize| Texture::new(size, size, vec![Color::black(); size * size]);
+        assert!(CubeMap::new((0..5).map(|_| face(2)).collect()).is_err());
+        let mut faces: Vec<Texture> = (0..5).map(|_| face(2)).collect();
+        faces.push(face(4));
+        assert!(CubeMap::new(faces).err().unwrap().contains("nz"));
+        assert!(CubeMap::new((0..6).map(|_| face(2)).collect()).is_ok());
+    }
+}
@@ -0,0 +1,181 @@
+use image::codecs::png::PngEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Ppm,
+    Tga,
+}
+
+impl ImageFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "ppm" => Some(ImageFormat::Ppm),
+            "tga" => Some(ImageFormat::Tga),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(ImageFormat::from_name)
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Ppm => "ppm",
+            ImageFormat::Tga => "tga",
+        }
+    }
+}
+
+// Los píxeles vienen empaquetados como 0xRRGGBB, igual que en Framebuffer::buffer
+pub fn write_image<W: Write>(
+    writer: &mut W,
+    format: ImageFormat,
+    width: usize,
+    height: usize,
+    pixels: &[u32],
+) -> io::Result<()> {
+    match format {
+        ImageFormat::Png => write_png(writer, width, height, pixels),
+        ImageFormat::Ppm => write_ppm(writer, width, height, pixels),
+        ImageFormat::Tga => write_tga(writer, width, height, pixels),
+    }
+}
+
+fn write_ppm<W: Write>(writer: &mut W, width: usize, height: usize, pixels: &[u32]) -> io::Result<()> {
+    write!(writer, "P6\n{} {}\n255\n", width, height)?;
+
+    let mut data = Vec::with_capacity(width * height * 3);
+    for &pixel in pixels {
+        data.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+    }
+    writer.write_all(&data)
+}
+
+fn write_tga<W: Write>(writer: &mut W, width: usize, height: usize, pixels: &[u32]) -> io::Result<()> {
+    if width > u16::MAX as usize || height > u16::MAX as usize {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "TGA admite como máximo 65535x65535"));
+    }
+
+    // Cabecera TGA sin compresión, color verdadero de 24 bits y origen arriba a la izquierda
+    let mut header = [0u8; 18];
+    header[2] = 2;
+    header[12..14].copy_from_slice(&(width as u16).to_le_bytes());
+    header[14..16].copy_from_slice(&(height as u16).to_le_bytes());
+    header[16] = 24;
+    header[17] = 0x20;
+    writer.write_all(&header)?;
+
+    let mut data = Vec::with_capacity(width * height * 3);
+    for &pixel in pixels {
+        data.extend_from_slice(&[pixel as u8, (pixel >> 8) as u8, (pixel >> 16) as u8]);
+    }
+    writer.write_all(&data)
+}
+
+fn write_png<W: Write>(writer: &mut W, width: usize, height: usize, pixels: &[u32]) -> io::Result<()> {
+    let mut data = Vec::with_capacity(width * height * 3);
+    for &pixel in pixels {
+        data.extend_from_slice(&[(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8]);
+    }
+
+    PngEncoder::new(writer)
+        .write_image(&data, width as u32, height as u32, ExtendedColorType::Rgb8)
+        .map_err(io::Error::other)
+}
+
+// Nombre con fecha y hora UTC, p. ej. "captura_20251104_153012_042.png"
+pub fn timestamped_filename(prefix: &str, format: ImageFormat) -> String {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = elapsed.as_secs();
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let seconds_of_day = secs % 86_400;
+
+    format!(
+        "{}_{:04}{:02}{:02}_{:02}{:02}{:02}_{:03}.{}",
+        prefix,
+        year,
+        month,
+        day,
+        seconds_of_day / 3600,
+        (seconds_of_day / 60) % 60,
+        seconds_of_day % 60,
+        elapsed.subsec_millis(),
+        format.extension()
+    )
+}
+
+// Conversión de días desde 1970-01-01 a fecha gregoriana (algoritmo de Howard Hinnant)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 2x1: rojo y azul
+    const PIXELS: [u32; 2] = [0xFF0000, 0x0000FF];
+
+    #[test]
+    fn ppm_has_header_and_rgb_data() {
+        let mut output = Vec::new();
+        write_ppm(&mut output, 2, 1, &PIXELS).unwrap();
+
+        let header = b"P6\n2 1\n255\n";
+        assert_eq!(&output[..header.len()], header);
+        assert_eq!(&output[header.len()..], &[255, 0, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn tga_has_header_and_bgr_data() {
+        let mut output = Vec::new();
+        write_tga(&mut output, 2, 1, &PIXELS).unwrap();
+
+        assert_eq!(output.len(), 18 + 6);
+        // Sin id ni paleta, color verdadero sin compresión
+        assert_eq!(&output[..3], &[0, 0, 2]);
+        assert_eq!(&output[12..16], &[2, 0, 1, 0]);
+        assert_eq!(output[16], 24);
+        // Origen arriba a la izquierda
+        assert_eq!(output[17], 0x20);
+        assert_eq!(&output[18..], &[0, 0, 255, 255, 0, 0]);
+    }
+
+    #[test]
+    fn tga_rejects_sizes_over_u16() {
+        let mut output = Vec::new();
+        assert!(write_tga(&mut output, 70_000, 1, &[]).is_err());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn civil_from_days_converts_known_dates() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(19_723), (2024, 1, 1));
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(20_088), (2024, 12, 31));
+    }
+}
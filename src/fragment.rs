@@ -1,33 +1,36 @@
-use nalgebra_glm::Vec2;
-use crate::color::Color;
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use crate::vertex::MAX_VARYINGS;
 
 #[derive(Clone, Debug)]
 pub struct Fragment {
     pub position: Vec2,
-    pub color: Color,
-    pub depth: f32,
     pub normal: nalgebra_glm::Vec3,
-    pub intensity: f32,
+    // Tangente interpolada en xyz y signo de la bitangente en w
+    pub tangent: Vec4,
+    pub tex_coords: Vec2,
+    // Cambio de las UV al avanzar un píxel en x e y, medido dentro del quad de 2x2
+    pub tex_coords_dx: Vec2,
+    pub tex_coords_dy: Vec2,
+    pub world_position: Vec3,
+    pub varyings: [f32; MAX_VARYINGS],
 }
 
 impl Fragment {
-    pub fn new(x: f32, y: f32, color: Color, depth: f32) -> Self {
+    pub fn new_with_normal(x: f32, y: f32, normal: nalgebra_glm::Vec3) -> Self {
         Fragment {
             position: Vec2::new(x, y),
-            color,
-            depth,
-            normal: nalgebra_glm::Vec3::new(0.0, 0.0, 1.0),
-            intensity: 1.0,
+            normal,
+            tangent: Vec4::new(1.0, 0.0, 0.0, 1.0),
+            tex_coords: Vec2::new(0.0, 0.0),
+            tex_coords_dx: Vec2::new(0.0, 0.0),
+            tex_coords_dy: Vec2::new(0.0, 0.0),
+            world_position: Vec3::new(0.0, 0.0, 0.0),
+            varyings: [0.0; MAX_VARYINGS],
         }
     }
 
-    pub fn new_with_normal(x: f32, y: f32, color: Color, depth: f32, normal: nalgebra_glm::Vec3, intensity: f32) -> Self {
-        Fragment {
-            position: Vec2::new(x, y),
-            color,
-            depth,
-            normal,
-            intensity,
-        }
+    // Vector unitario desde el fragmento hacia la cámara
+    pub fn view_dir(&self, camera_position: &Vec3) -> Vec3 {
+        (camera_position - self.world_position).normalize()
     }
-}
\ No newline at end of file
+}
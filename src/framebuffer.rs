@@ -1,55 +1,566 @@
 use crate::color::Color;
+use crate::export::{self, ImageFormat};
+use crate::pipeline::{BlendMode, RenderState};
+use crate::tonemap::ToneMapping;
+use nalgebra_glm::{Vec3, Vec4};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
 
 pub const SCREEN_WIDTH: usize = 800;
 pub const SCREEN_HEIGHT: usize = 600;
+pub const TILE_SIZE: usize = 64;
+pub const MAX_SAMPLES: usize = 8;
 
+// Margen de profundidad para que las líneas sobre una superficie no pierdan el
+// depth test contra los píxeles del propio triángulo
+const LINE_DEPTH_BIAS: f32 = 1e-4;
+
+// Posiciones de muestreo MSAA en 1/16 de píxel relativas al centro del píxel
+// (patrones estándar de Direct3D, con y hacia abajo)
+const SAMPLE_PATTERN_1X: [(i32, i32); 1] = [(0, 0)];
+const SAMPLE_PATTERN_2X: [(i32, i32); 2] = [(4, 4), (-4, -4)];
+const SAMPLE_PATTERN_4X: [(i32, i32); 4] = [(-2, -6), (6, -2), (-6, 2), (2, 6)];
+const SAMPLE_PATTERN_8X: [(i32, i32); 8] = [
+    (1, -3), (-1, 3), (5, 1), (-3, -5),
+    (-5, 5), (-7, -1), (3, 7), (7, -7),
+];
+
+pub fn sample_pattern(samples: usize) -> &'static [(i32, i32)] {
+    match samples {
+        2 => &SAMPLE_PATTERN_2X,
+        4 => &SAMPLE_PATTERN_4X,
+        8 => &SAMPLE_PATTERN_8X,
+        _ => &SAMPLE_PATTERN_1X,
+    }
+}
+
+pub fn is_valid_sample_count(samples: usize) -> bool {
+    matches!(samples, 1 | 2 | 4 | 8)
+}
+
+// Fragmento transparente guardado en el A-buffer hasta el final del frame
+#[derive(Debug, Clone, Copy)]
+pub struct TransparentFragment {
+    pub depth: f32,
+    // Radiancia lineal con la opacidad en `w`
+    pub color: Vec4,
+    pub blend_mode: BlendMode,
+    // Bit i = la muestra MSAA i está cubierta y pasó el depth test
+    pub coverage: u8,
+}
+
+// `buffer` es la imagen final (0xRRGGBB) que se muestra y exporta. El color y la
+// profundidad se guardan por muestra en `sample_buffer` y `zbuffer`, con las
+// `samples` muestras de cada píxel contiguas. El color de las muestras es
+// radiancia lineal sin límite superior (HDR); `resolve` aplica el tone mapping,
+// promedia las muestras y codifica el resultado en sRGB en `buffer`.
+// `stencil_buffer` guarda 8 bits por muestra para el stencil test.
+// Con transparencia independiente del orden, `fragment_lists` guarda por píxel
+// los fragmentos transparentes, que `resolve` mezcla antes del tone mapping.
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
+    pub samples: usize,
     pub buffer: Vec<u32>,
+    pub sample_buffer: Vec<Vec3>,
     pub zbuffer: Vec<f32>,
+    pub stencil_buffer: Vec<u8>,
+    // Valor con el que `clear` llena el stencil buffer
+    pub stencil_clear_value: u8,
+    // Vacío si la transparencia independiente del orden está desactivada
+    pub fragment_lists: Vec<Vec<TransparentFragment>>,
     pub background_color: Color,
-    pub current_color: Color,
+    pub tone_mapping: ToneMapping,
+    pub exposure: f32,
 }
 
 impl Framebuffer {
     pub fn new(width: usize, height: usize) -> Self {
+        Framebuffer::new_with_samples(width, height, 1)
+    }
+
+    pub fn new_with_samples(width: usize, height: usize, samples: usize) -> Self {
+        let samples = if is_valid_sample_count(samples) { samples } else { 1 };
         Framebuffer {
             width,
             height,
+            samples,
             buffer: vec![0; width * height],
-            zbuffer: vec![f32::INFINITY; width * height],
+            sample_buffer: vec![Vec3::zeros(); width * height * samples],
+            zbuffer: vec![f32::INFINITY; width * height * samples],
+            stencil_buffer: vec![0; width * height * samples],
+            stencil_clear_value: 0,
+            fragment_lists: Vec::new(),
             background_color: Color::black(),
-            current_color: Color::new(255, 255, 255),
+            tone_mapping: ToneMapping::None,
+            exposure: 1.0,
         }
     }
 
+    // Solo profundidad, para pasadas como los shadow maps: sin color ni
+    // stencil, los tiles descartan el color y el stencil test siempre pasa
+    pub fn new_depth_only(width: usize, height: usize) -> Self {
+        Framebuffer {
+            width,
+            height,
+            buffer: Vec::new(),
+            sample_buffer: Vec::new(),
+            zbuffer: vec![f32::INFINITY; width * height],
+            stencil_buffer: Vec::new(),
+            ..Framebuffer::new(0, 0)
+        }
+    }
+
+    pub fn is_depth_only(&self) -> bool {
+        self.sample_buffer.is_empty() && !self.zbuffer.is_empty()
+    }
+
+    // Cambia el número de muestras por píxel (1, 2, 4 u 8) y reserva los buffers
+    pub fn set_samples(&mut self, samples: usize) {
+        if !is_valid_sample_count(samples) || samples == self.samples {
+            return;
+        }
+        let depth_only = self.is_depth_only();
+        self.samples = samples;
+        self.zbuffer = vec![f32::INFINITY; self.width * self.height * samples];
+        if !depth_only {
+            self.sample_buffer = vec![Vec3::zeros(); self.width * self.height * samples];
+            self.stencil_buffer = vec![0; self.width * self.height * samples];
+        }
+        self.clear();
+    }
+
     pub fn clear(&mut self) {
-        let bg_color = self.background_color.to_hex();
-        for pixel in self.buffer.iter_mut() {
-            *pixel = bg_color;
+        self.buffer.fill(self.background_color.to_hex());
+        self.sample_buffer.fill(self.background_color.to_linear());
+        self.zbuffer.fill(f32::INFINITY);
+        self.stencil_buffer.fill(self.stencil_clear_value);
+        // Se conserva la capacidad de cada lista para no reservar memoria cada frame
+        self.fragment_lists.par_iter_mut().for_each(|list| list.clear());
+    }
+
+    // Activa el A-buffer: desde ahí los fragmentos con mezcla transparente se
+    // acumulan por píxel en lugar de mezclarse al rasterizar
+    pub fn set_order_independent(&mut self, enabled: bool) {
+        if enabled == self.is_order_independent() {
+            return;
         }
-        for depth in self.zbuffer.iter_mut() {
-            *depth = f32::INFINITY;
+        self.fragment_lists = if enabled { vec![Vec::new(); self.width * self.height] } else { Vec::new() };
+    }
+
+    pub fn is_order_independent(&self) -> bool {
+        !self.fragment_lists.is_empty()
+    }
+
+    // Mezcla los fragmentos transparentes de cada píxel del más lejano al más
+    // cercano sobre el color opaco de cada muestra que cubren, y vacía las
+    // listas. Los que quedaron detrás de un opaco ya se descartaron al rasterizar.
+    pub fn composite_fragments(&mut self) {
+        if !self.is_order_independent() {
+            return;
+        }
+        let samples = self.samples;
+
+        self.sample_buffer
+            .par_chunks_mut(self.width * samples)
+            .zip(self.fragment_lists.par_chunks_mut(self.width))
+            .for_each(|(sample_row, lists)| {
+                for (pixel_samples, list) in sample_row.chunks_mut(samples).zip(lists.iter_mut()) {
+                    if list.is_empty() {
+                        continue;
+                    }
+                    // Orden estable: a igual profundidad se respeta el orden de envío
+                    list.sort_by(|a, b| b.depth.total_cmp(&a.depth));
+                    for fragment in list.iter() {
+                        for (sample, value) in pixel_samples.iter_mut().enumerate() {
+                            if fragment.coverage & (1 << sample) != 0 {
+                                *value = fragment.blend_mode.blend(fragment.color, *value);
+                            }
+                        }
+                    }
+                    list.clear();
+                }
+            });
+    }
+
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping, exposure: f32) {
+        self.tone_mapping = tone_mapping;
+        self.exposure = exposure;
+    }
+
+    // Convierte las muestras HDR de cada píxel en el color final de `buffer`. El
+    // tone mapping se aplica a cada muestra antes de promediar, así un borde
+    // muy brillante no se come el anti-aliasing; el promedio se hace en
+    // espacio lineal y al final se codifica en sRGB. Antes se componen los
+    // fragmentos pendientes del A-buffer.
+    pub fn resolve(&mut self) {
+        self.composite_fragments();
+        let samples = self.samples;
+        let (tone_mapping, exposure) = (self.tone_mapping, self.exposure);
+
+        self.buffer
+            .par_chunks_mut(self.width)
+            .zip(self.sample_buffer.par_chunks(self.width * samples))
+            .for_each(|(row, sample_row)| {
+                for (pixel, pixel_samples) in row.iter_mut().zip(sample_row.chunks(samples)) {
+                    let sum: Vec3 = pixel_samples
+                        .iter()
+                        .map(|&sample| tone_mapping.apply(sample, exposure))
+                        .sum();
+                    *pixel = Color::from_linear(sum / samples as f32).to_hex();
+                }
+            });
+    }
+
+    // Camino de escritura por fragmento de líneas y puntos. En cada muestra del
+    // píxel hace el stencil test y el depth test (con un pequeño bias para no
+    // perder contra la propia superficie), aplica las operaciones de stencil de
+    // `state` y, si escribe color, mezcla `color` con la cobertura que trae en
+    // `w`. No escribe profundidad.
+    pub fn point(&mut self, x: i32, y: i32, depth: f32, color: Vec4, depth_test: bool, state: &RenderState) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height || color.w <= 0.0 {
+            return;
+        }
+
+        let first = (y as usize * self.width + x as usize) * self.samples;
+        let coverage = color.w;
+        let color = color.xyz();
+        for index in first..first + self.samples {
+            let depth_passed = !depth_test || depth - LINE_DEPTH_BIAS < self.zbuffer[index];
+            let visible = match self.stencil_buffer.get_mut(index) {
+                Some(value) => state.stencil.test_and_update(value, depth_passed),
+                None => depth_passed,
+            };
+            if let Some(sample) = self.sample_buffer.get_mut(index).filter(|_| visible && state.color_write) {
+                *sample = sample.lerp(&color, coverage);
+            }
+        }
+    }
+
+    // Línea entre dos puntos en coordenadas de pantalla (z ya dividida por w, lineal
+    // en pantalla). Con `antialiased` usa el algoritmo de Xiaolin Wu.
+    pub fn line(&mut self, from: Vec3, to: Vec3, color: Color, depth_test: bool, antialiased: bool, state: &RenderState) {
+        let color = color.to_linear();
+        // Se desplaza medio píxel para que las coordenadas enteras sean centros de píxel
+        let (mut x0, mut y0, mut z0) = (from.x - 0.5, from.y - 0.5, from.z);
+        let (mut x1, mut y1, mut z1) = (to.x - 0.5, to.y - 0.5, to.z);
+
+        let steep = (y1 - y0).abs() > (x1 - x0).abs();
+        if steep {
+            std::mem::swap(&mut x0, &mut y0);
+            std::mem::swap(&mut x1, &mut y1);
+        }
+        if x0 > x1 {
+            std::mem::swap(&mut x0, &mut x1);
+            std::mem::swap(&mut y0, &mut y1);
+            std::mem::swap(&mut z0, &mut z1);
+        }
+
+        let dx = x1 - x0;
+        let gradient = if dx.abs() < f32::EPSILON { 0.0 } else { (y1 - y0) / dx };
+
+        // Solo se recorre la parte de la línea que cae dentro del framebuffer
+        let limit = if steep { self.height } else { self.width } as f32;
+        let start = x0.round().max(0.0) as i32;
+        let end = x1.round().min(limit - 1.0) as i32;
+
+        for x in start..=end {
+            let t = if dx.abs() < f32::EPSILON { 0.0 } else { ((x as f32 - x0) / dx).clamp(0.0, 1.0) };
+            let y = y0 + gradient * (x as f32 - x0);
+            let z = z0 + (z1 - z0) * t;
+
+            let mut plot = |px: i32, py: i32, coverage: f32| {
+                let fragment = Vec4::new(color.x, color.y, color.z, coverage);
+                if steep {
+                    self.point(py, px, z, fragment, depth_test, state);
+                } else {
+                    self.point(px, py, z, fragment, depth_test, state);
+                }
+            };
+
+            if antialiased {
+                let base = y.floor();
+                let fraction = y - base;
+                plot(x, base as i32, 1.0 - fraction);
+                plot(x, base as i32 + 1, fraction);
+            } else {
+                plot(x, y.round() as i32, 1.0);
+            }
         }
     }
 
-    pub fn set_current_color(&mut self, color: Color) {
-        self.current_color = color;
+    // Punto cuadrado de `size` píxeles centrado en `center` (coordenadas de pantalla)
+    pub fn dot(&mut self, center: Vec3, size: i32, color: Color, depth_test: bool, state: &RenderState) {
+        let fragment = color.to_linear().push(1.0);
+        let min_x = (center.x - size as f32 / 2.0).round() as i32;
+        let min_y = (center.y - size as f32 / 2.0).round() as i32;
+
+        for y in min_y..min_y + size {
+            for x in min_x..min_x + size {
+                self.point(x, y, center.z, fragment, depth_test, state);
+            }
+        }
     }
 
-    pub fn point(&mut self, x: usize, y: usize, depth: f32) {
-        if x < self.width && y < self.height {
-            let index = y * self.width + x;
-            
-            if depth < self.zbuffer[index] {
-                self.buffer[index] = self.current_color.to_hex();
-                self.zbuffer[index] = depth;
+    // Divide el framebuffer en tiles de `tile_size` x `tile_size` píxeles. Cada tile
+    // toma prestadas sus propias filas de `sample_buffer`, `zbuffer`,
+    // `stencil_buffer` y `fragment_lists`, así que los tiles pueden escribirse en paralelo sin
+    // compartir memoria.
+    pub fn tiles_mut(&mut self, tile_size: usize) -> Vec<Tile<'_>> {
+        let tiles_x = self.width.div_ceil(tile_size);
+        let tiles_y = self.height.div_ceil(tile_size);
+
+        let mut tiles = Vec::with_capacity(tiles_x * tiles_y);
+        for ty in 0..tiles_y {
+            for tx in 0..tiles_x {
+                let x = tx * tile_size;
+                let y = ty * tile_size;
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: tile_size.min(self.width - x),
+                    height: tile_size.min(self.height - y),
+                    samples: self.samples,
+                    color_rows: Vec::with_capacity(tile_size),
+                    depth_rows: Vec::with_capacity(tile_size),
+                    stencil_rows: Vec::with_capacity(tile_size),
+                    fragment_rows: Vec::new(),
+                });
+            }
+        }
+
+        let row_len = self.width * self.samples;
+        let segment_len = tile_size * self.samples;
+
+        // En un framebuffer de solo profundidad no hay filas de color ni de stencil
+        let mut color_rows = self.sample_buffer.chunks_mut(row_len);
+        let mut stencil_rows = self.stencil_buffer.chunks_mut(row_len);
+        for (row_index, depth_row) in self.zbuffer.chunks_mut(row_len).enumerate() {
+            let first_tile = (row_index / tile_size) * tiles_x;
+            for (tx, depth_segment) in depth_row.chunks_mut(segment_len).enumerate() {
+                tiles[first_tile + tx].depth_rows.push(depth_segment);
+            }
+            if let Some(color_row) = color_rows.next() {
+                for (tx, color_segment) in color_row.chunks_mut(segment_len).enumerate() {
+                    tiles[first_tile + tx].color_rows.push(color_segment);
+                }
+            }
+            if let Some(stencil_row) = stencil_rows.next() {
+                for (tx, stencil_segment) in stencil_row.chunks_mut(segment_len).enumerate() {
+                    tiles[first_tile + tx].stencil_rows.push(stencil_segment);
+                }
+            }
+        }
+
+        for (row_index, list_row) in self.fragment_lists.chunks_mut(self.width).enumerate() {
+            let first_tile = (row_index / tile_size) * tiles_x;
+            for (tx, list_segment) in list_row.chunks_mut(tile_size).enumerate() {
+                tiles[first_tile + tx].fragment_rows.push(list_segment);
             }
         }
+
+        tiles
     }
 
     pub fn set_background_color(&mut self, color: Color) {
         self.background_color = color;
     }
-}
\ No newline at end of file
+
+    pub fn export<P: AsRef<Path>>(&self, path: P, format: ImageFormat) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        export::write_image(&mut writer, format, self.width, self.height, &self.buffer)?;
+        writer.flush()
+    }
+
+    // Elige el formato a partir de la extensión del archivo
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let format = ImageFormat::from_path(path).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Formato de imagen no soportado: {}", path.display()),
+            )
+        })?;
+        self.export(path, format)
+    }
+}
+
+// Región rectangular del framebuffer con acceso exclusivo a sus muestras
+pub struct Tile<'a> {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+    pub samples: usize,
+    // Vacías en un framebuffer de solo profundidad
+    color_rows: Vec<&'a mut [Vec3]>,
+    depth_rows: Vec<&'a mut [f32]>,
+    stencil_rows: Vec<&'a mut [u8]>,
+    // Una lista por píxel (no por muestra); vacío sin A-buffer
+    fragment_rows: Vec<&'a mut [Vec<TransparentFragment>]>,
+}
+
+impl Tile<'_> {
+    // Las coordenadas son del framebuffer completo, no relativas al tile
+    fn local_index(&self, x: usize, y: usize, sample: usize) -> Option<(usize, usize)> {
+        if x < self.x || y < self.y || x >= self.x + self.width || y >= self.y + self.height {
+            return None;
+        }
+        Some(((x - self.x) * self.samples + sample, y - self.y))
+    }
+
+    // Stencil test y depth test de una muestra, con las operaciones de stencil
+    // de `state` según el resultado. Devuelve si la muestra es visible.
+    pub fn test(&mut self, x: usize, y: usize, sample: usize, depth: f32, state: &RenderState) -> bool {
+        let Some((lx, ly)) = self.local_index(x, y, sample) else {
+            return false;
+        };
+        let depth_passed = depth < self.depth_rows[ly][lx];
+        match self.stencil_rows.get_mut(ly) {
+            Some(stencil_row) => state.stencil.test_and_update(&mut stencil_row[lx], depth_passed),
+            None => depth_passed,
+        }
+    }
+
+    // Escribe sin comprobar profundidad; usar después de `test`. `color` es
+    // radiancia lineal con la opacidad en `w`, como la devuelve
+    // `FragmentShader::shade_hdr`, y se mezcla según `state`
+    pub fn write(&mut self, x: usize, y: usize, sample: usize, depth: f32, color: Vec4, state: &RenderState) {
+        if let Some((lx, ly)) = self.local_index(x, y, sample) {
+            if let Some(color_row) = self.color_rows.get_mut(ly).filter(|_| state.color_write) {
+                color_row[lx] = state.blend_mode.blend(color, color_row[lx]);
+            }
+            if state.depth_write {
+                self.depth_rows[ly][lx] = depth;
+            }
+        }
+    }
+
+    // Escribe el color de un fragmento en las muestras de `coverage` con sus
+    // profundidades. Con A-buffer los fragmentos transparentes se guardan en la
+    // lista del píxel para mezclarse ordenados al resolver.
+    pub fn write_fragment(
+        &mut self,
+        x: usize,
+        y: usize,
+        depths: &[f32],
+        coverage: u8,
+        color: Vec4,
+        state: &RenderState,
+    ) {
+        if state.is_transparent() && state.color_write && !self.fragment_rows.is_empty() {
+            if x < self.x || y < self.y || x >= self.x + self.width || y >= self.y + self.height {
+                return;
+            }
+            // Se ordena por la profundidad media de las muestras cubiertas
+            let covered = (0..self.samples).filter(|&sample| coverage & (1 << sample) != 0);
+            let (sum, count) = covered.fold((0.0, 0), |(sum, count), sample| (sum + depths[sample], count + 1));
+            if count == 0 {
+                return;
+            }
+            self.fragment_rows[y - self.y][x - self.x].push(TransparentFragment {
+                depth: sum / count as f32,
+                color,
+                blend_mode: state.blend_mode,
+                coverage,
+            });
+            return;
+        }
+
+        for (sample, &depth) in depths.iter().enumerate().take(self.samples) {
+            if coverage & (1 << sample) != 0 {
+                self.write(x, y, sample, depth, color, state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKGROUND: Vec3 = Vec3::new(0.0, 0.0, 1.0);
+
+    // Framebuffer de 1x1 con fondo azul y A-buffer activo
+    fn abuffer(samples: usize) -> Framebuffer {
+        let mut framebuffer = Framebuffer::new_with_samples(1, 1, samples);
+        framebuffer.set_order_independent(true);
+        framebuffer.sample_buffer.fill(BACKGROUND);
+        framebuffer
+    }
+
+    fn submit(framebuffer: &mut Framebuffer, fragments: &[(f32, Vec4, BlendMode)], coverage: u8) {
+        let samples = framebuffer.samples;
+        let mut tiles = framebuffer.tiles_mut(16);
+        for &(depth, color, blend_mode) in fragments {
+            tiles[0].write_fragment(0, 0, &vec![depth; samples], coverage, color, &RenderState::blended(blend_mode));
+        }
+    }
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).abs().max() < 1e-6
+    }
+
+    #[test]
+    fn composite_is_independent_of_submission_order() {
+        let fragments = [
+            (0.3, Vec4::new(1.0, 0.0, 0.0, 0.5), BlendMode::Alpha),
+            (0.6, Vec4::new(0.0, 1.0, 0.0, 0.25), BlendMode::Alpha),
+            (0.1, Vec4::new(0.2, 0.2, 0.0, 0.5), BlendMode::Additive),
+        ];
+        // Del más lejano al más cercano sobre el fondo
+        let expected = [1, 0, 2].iter().fold(BACKGROUND, |dst, &i| fragments[i].2.blend(fragments[i].1, dst));
+
+        for order in [[0, 1, 2], [2, 1, 0], [1, 2, 0]] {
+            let mut framebuffer = abuffer(1);
+            let shuffled: Vec<_> = order.iter().map(|&i| fragments[i]).collect();
+            submit(&mut framebuffer, &shuffled, 1);
+            assert_eq!(framebuffer.fragment_lists[0].len(), 3);
+
+            framebuffer.composite_fragments();
+            assert!(close(framebuffer.sample_buffer[0], expected), "{:?}", order);
+            assert!(framebuffer.fragment_lists[0].is_empty());
+        }
+    }
+
+    #[test]
+    fn alpha_controls_how_much_of_the_background_remains() {
+        let mut framebuffer = abuffer(1);
+        submit(&mut framebuffer, &[(0.5, Vec4::new(1.0, 0.0, 0.0, 0.25), BlendMode::Alpha)], 1);
+        framebuffer.composite_fragments();
+        assert!(close(framebuffer.sample_buffer[0], Vec3::new(0.25, 0.0, 0.75)));
+
+        let mut framebuffer = abuffer(1);
+        submit(&mut framebuffer, &[(0.5, Vec4::new(1.0, 0.0, 0.0, 0.0), BlendMode::Alpha)], 1);
+        framebuffer.composite_fragments();
+        assert!(close(framebuffer.sample_buffer[0], BACKGROUND));
+    }
+
+    #[test]
+    fn only_covered_samples_are_blended() {
+        let mut framebuffer = abuffer(4);
+        submit(&mut framebuffer, &[(0.5, Vec4::new(1.0, 0.0, 0.0, 1.0), BlendMode::Alpha)], 0b0101);
+        framebuffer.composite_fragments();
+        let red = Vec3::new(1.0, 0.0, 0.0);
+        let expected = [red, BACKGROUND, red, BACKGROUND];
+        for (sample, value) in framebuffer.sample_buffer.iter().enumerate() {
+            assert!(close(*value, expected[sample]), "muestra {}", sample);
+        }
+    }
+
+    #[test]
+    fn opaque_fragments_bypass_the_lists() {
+        let mut framebuffer = abuffer(1);
+        framebuffer.tiles_mut(16)[0].write_fragment(0, 0, &[0.5], 1, Vec4::new(1.0, 1.0, 1.0, 1.0), &RenderState::opaque());
+        assert!(framebuffer.fragment_lists[0].is_empty());
+        assert_eq!(framebuffer.sample_buffer[0], Vec3::new(1.0, 1.0, 1.0));
+        assert_eq!(framebuffer.zbuffer[0], 0.5);
+
+        // Sin A-buffer los transparentes se mezclan en el acto
+        let mut framebuffer = Framebuffer::new(1, 1);
+        submit(&mut framebuffer, &[(0.5, Vec4::new(1.0, 0.0, 0.0, 0.5), BlendMode::Alpha)], 1);
+        assert!(close(framebuffer.sample_buffer[0], Vec3::new(0.5, 0.0, 0.0)));
+    }
+}
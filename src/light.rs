@@ -0,0 +1,179 @@
+use nalgebra_glm::{rotate_vec3, Vec3};
+use crate::color::Color;
+
+// Atenuación por distancia: 1 / (constant + linear * d + quadratic * d²)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    pub fn new(constant: f32, linear: f32, quadratic: f32) -> Self {
+        Attenuation { constant, linear, quadratic }
+    }
+
+    pub fn factor(&self, distance: f32) -> f32 {
+        1.0 / (self.constant + self.linear * distance + self.quadratic * distance * distance)
+    }
+}
+
+impl Default for Attenuation {
+    // Alcance aproximado de 50 unidades
+    fn default() -> Self {
+        Attenuation::new(1.0, 0.09, 0.032)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LightKind {
+    // `direction` es hacia donde viaja la luz, como un Sun de Blender
+    Directional { direction: Vec3 },
+    Point { position: Vec3, attenuation: Attenuation },
+    // Los conos son semiángulos en radianes; entre inner y outer la luz se desvanece
+    Spot {
+        position: Vec3,
+        direction: Vec3,
+        inner_cone: f32,
+        outer_cone: f32,
+        attenuation: Attenuation,
+    },
+}
+
+// Contribución de una luz en un punto
+#[derive(Debug, Clone, Copy)]
+pub struct LightSample {
+    // Vector unitario desde el punto hacia la luz
+    pub direction: Vec3,
+    // color * intensidad * atenuación, por canal
+    pub radiance: Vec3,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Light {
+    pub name: String,
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+    pub enabled: bool,
+}
+
+impl Light {
+    pub fn new(name: &str, kind: LightKind, color: Color, intensity: f32) -> Self {
+        Light { name: name.to_string(), kind, color, intensity, enabled: true }
+    }
+
+    pub fn directional(name: &str, direction: Vec3, color: Color, intensity: f32) -> Self {
+        Light::new(name, LightKind::Directional { direction: direction.normalize() }, color, intensity)
+    }
+
+    pub fn point(name: &str, position: Vec3, color: Color, intensity: f32) -> Self {
+        Light::new(name, LightKind::Point { position, attenuation: Attenuation::default() }, color, intensity)
+    }
+
+    pub fn spot(name: &str, position: Vec3, target: Vec3, inner_cone: f32, outer_cone: f32, color: Color, intensity: f32) -> Self {
+        Light::new(
+            name,
+            LightKind::Spot {
+                position,
+                direction: (target - position).normalize(),
+                inner_cone,
+                outer_cone: outer_cone.max(inner_cone),
+                attenuation: Attenuation::default(),
+            },
+            color,
+            intensity,
+        )
+    }
+
+    pub fn with_enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    // Dirección hacia la luz y radiancia que llega a `point`; None si la luz
+    // está apagada, el punto coincide con la posición de la luz o queda fuera
+    // del cono de un spot
+    pub fn illuminate(&self, point: &Vec3) -> Option<LightSample> {
+        if !self.enabled {
+            return None;
+        }
+
+        let (direction, falloff) = match self.kind {
+            LightKind::Directional { direction } => (-direction, 1.0),
+            LightKind::Point { position, attenuation } => {
+                let to_light = position - point;
+                let distance = to_light.magnitude();
+                if distance <= f32::EPSILON {
+                    return None;
+                }
+                (to_light / distance, attenuation.factor(distance))
+            }
+            LightKind::Spot { position, direction, inner_cone, outer_cone, attenuation } => {
+                let to_light = position - point;
+                let distance = to_light.magnitude();
+                if distance <= f32::EPSILON {
+                    return None;
+                }
+                let to_light = to_light / distance;
+
+                let cos_angle = nalgebra_glm::dot(&-to_light, &direction);
+                let (cos_inner, cos_outer) = (inner_cone.cos(), outer_cone.cos());
+                let cone = if cos_inner - cos_outer > f32::EPSILON {
+                    ((cos_angle - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0)
+                } else if cos_angle >= cos_outer {
+                    1.0
+                } else {
+                    0.0
+                };
+                (to_light, cone * attenuation.factor(distance))
+            }
+        };
+
+        if falloff <= 0.0 || !falloff.is_finite() {
+            return None;
+        }
+
+        // El color de la luz está en sRGB; la radiancia se expresa en lineal
+        let scale = self.intensity * falloff;
+        Some(LightSample { direction, radiance: self.color.to_linear() * scale })
+    }
+
+    // Gira la luz alrededor del origen: `yaw` sobre el eje Y y `pitch` sobre el eje X
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        let rotate = |v: Vec3| {
+            let v = rotate_vec3(&v, yaw, &Vec3::new(0.0, 1.0, 0.0));
+            rotate_vec3(&v, pitch, &Vec3::new(1.0, 0.0, 0.0))
+        };
+
+        match &mut self.kind {
+            LightKind::Directional { direction } => *direction = rotate(*direction),
+            LightKind::Point { position, .. } => *position = rotate(*position),
+            LightKind::Spot { position, direction, .. } => {
+                *position = rotate(*position);
+                *direction = rotate(*direction);
+            }
+        }
+    }
+}
+
+// Iluminación de tres puntos para evaluar modelos. Solo la luz principal
+// empieza encendida, así que el render por defecto equivale a una única luz
+// direccional; el relleno y el contraluz se activan con --lights o en ejecución.
+pub fn three_point_rig() -> Vec<Light> {
+    vec![
+        Light::directional("key", Vec3::new(-0.5, 0.5, 1.0), Color::white(), 1.0),
+        Light::point("fill", Vec3::new(-3.0, 1.0, 3.0), Color::new(150, 180, 255), 0.6).with_enabled(false),
+        Light::spot(
+            "rim",
+            Vec3::new(0.0, 2.0, -4.0),
+            Vec3::new(0.0, 0.0, 0.0),
+            0.35,
+            0.6,
+            Color::new(255, 230, 200),
+            1.5,
+        )
+        .with_enabled(false),
+    ]
+}
@@ -6,65 +6,277 @@ mod vertex;
 mod fragment;
 mod shaders;
 mod camera;
+mod pipeline;
+mod clipping;
+mod config;
+mod export;
+mod renderer;
+mod scene;
+mod shader_registry;
+mod material;
+mod texture;
+mod pbr;
+mod light;
+mod cubemap;
+mod shadow;
+mod stencil;
+mod tonemap;
 
 use crate::color::Color;
-use crate::framebuffer::{Framebuffer, SCREEN_WIDTH, SCREEN_HEIGHT};
-use crate::triangle::Triangle;
+use crate::framebuffer::Framebuffer;
 use crate::obj_loader::Model;
-use crate::vertex::Vertex;
-use crate::shaders::{vertex_shader, fragment_shader, create_model_matrix, create_viewport_matrix, Uniforms};
+use crate::shaders::{create_viewport_matrix, NormalMappedShader, ShaderProgram, TextureShader, Uniforms};
 use crate::camera::Camera;
+use crate::renderer::{render_scene, render_skybox};
+use crate::config::Config;
+use crate::export::timestamped_filename;
+use crate::scene::Scene;
+use crate::shader_registry::{BuiltinResources, ShaderRegistry, MAX_SHADER_KEYS};
+use crate::light::{three_point_rig, Light};
+use crate::texture::Texture;
+use crate::cubemap::CubeMap;
+use crate::shadow::{render_shadow_maps, ShadowCache, ShadowMap};
+use crate::pbr::{PbrMaterial, PbrShader};
 
 use minifb::{Key, Window, WindowOptions};
-use nalgebra_glm::{Vec3, Mat4};
+use nalgebra_glm::{Mat4, Vec3};
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::sync::Arc;
 use std::time::Instant;
 
-fn render(
-    framebuffer: &mut Framebuffer,
-    uniforms: &Uniforms,
-    vertex_array: &[Vertex],
-    shader_type: &str,
-) {
-    let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
-    for vertex in vertex_array {
-        let transformed = vertex_shader(vertex, uniforms);
-        transformed_vertices.push(transformed);
+const SHADER_KEYS: [Key; MAX_SHADER_KEYS] = [
+    Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5,
+    Key::Key6, Key::Key7, Key::Key8, Key::Key9, Key::Key0,
+];
+
+fn default_camera() -> Camera {
+    Camera::new(
+        Vec3::new(0.0, 0.0, 5.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    )
+}
+
+// La matriz de modelo la reemplaza cada nodo de la escena al dibujarse
+fn create_uniforms(
+    camera: &Camera,
+    lights: &[Light],
+    shadow_maps: Vec<Option<Arc<ShadowMap>>>,
+    width: usize,
+    height: usize,
+    time: f32,
+) -> Uniforms {
+    Uniforms {
+        model_matrix: Mat4::identity(),
+        view_matrix: camera.get_view_matrix(),
+        projection_matrix: camera.get_projection_matrix(width as f32 / height as f32),
+        viewport_matrix: create_viewport_matrix(width as f32, height as f32),
+        time,
+        lights: lights.to_vec(),
+        shadow_maps,
+        camera_position: camera.eye,
     }
+}
 
-    let mut triangles = Vec::new();
-    for i in (0..transformed_vertices.len()).step_by(3) {
-        if i + 2 < transformed_vertices.len() {
-            triangles.push(Triangle::new_from_vertices(
-                transformed_vertices[i].clone(),
-                transformed_vertices[i + 1].clone(),
-                transformed_vertices[i + 2].clone(),
-            ));
+// Rig de tres puntos; si se pasó --lights solo quedan encendidas las luces nombradas
+fn create_lights(config: &Config) -> Vec<Light> {
+    let mut lights = three_point_rig();
+
+    if let Some(names) = &config.lights {
+        for name in names {
+            if !lights.iter().any(|light| &light.name == name) {
+                let available: Vec<&str> = lights.iter().map(|light| light.name.as_str()).collect();
+                eprintln!("Luz desconocida: {} (disponibles: {})", name, available.join(", "));
+                process::exit(2);
+            }
+        }
+        for light in &mut lights {
+            light.enabled = names.contains(&light.name);
         }
     }
 
-    let mut all_fragments = Vec::new();
-    for triangle in &triangles {
-        let fragments = triangle.draw(framebuffer);
-        all_fragments.extend(fragments);
+    lights
+}
+
+// Textura del shader `textured`: la de --texture o un tablero de ajedrez
+fn create_texture_shader(config: &Config) -> TextureShader {
+    let texture = match &config.texture_path {
+        Some(path) => {
+            let texture = Texture::load(path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+            println!("Textura cargada: {}x{}", texture.width, texture.height);
+            texture
+        }
+        None => Texture::default(),
+    };
+
+    let texture = texture
+        .with_filter(config.texture_filter)
+        .with_wrap(config.texture_wrap)
+        .with_anisotropy(config.anisotropy);
+    TextureShader::new(Arc::new(texture))
+}
+
+// Normal map del shader `normal_mapped`: el de --normal-map o paneles de ejemplo
+fn create_normal_mapped_shader(config: &Config) -> NormalMappedShader {
+    let normal_map = match &config.normal_map_path {
+        Some(path) => Texture::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        }),
+        None => Texture::panel_normal_map(256, 4),
+    };
+
+    let normal_map = normal_map
+        .with_filter(config.texture_filter)
+        .with_wrap(config.texture_wrap)
+        .with_anisotropy(config.anisotropy);
+    NormalMappedShader::new(Arc::new(normal_map))
+}
+
+// Material del shader `pbr`: los valores del Principled BSDF de Blender,
+// multiplicados por los mapas de --pbr-albedo, --pbr-metal-rough y --pbr-ao
+fn create_pbr_shader(config: &Config) -> PbrShader {
+    let load_map = |path: &String| {
+        let texture = Texture::load(path).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1);
+        });
+        let texture = texture
+            .with_filter(config.texture_filter)
+            .with_wrap(config.texture_wrap)
+            .with_anisotropy(config.anisotropy);
+        Arc::new(texture)
+    };
+
+    let mut material = PbrMaterial::default();
+    if let Some(path) = &config.pbr_albedo_path {
+        material = material.with_albedo_map(load_map(path));
     }
+    if let Some(path) = &config.pbr_metallic_roughness_path {
+        // Como en glTF, con mapa los factores valen 1 y el mapa da los valores tal cual
+        material.metallic = 1.0;
+        material.roughness = 1.0;
+        material = material.with_metallic_roughness_map(load_map(path));
+    }
+    if let Some(path) = &config.pbr_ao_path {
+        material = material.with_ao_map(load_map(path));
+    }
+    PbrShader::new(material)
+}
 
-    for fragment in all_fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
-        
-        if x < framebuffer.width && y < framebuffer.height {
-            let shaded_color = fragment_shader(&fragment, uniforms, shader_type);
-            framebuffer.set_current_color(shaded_color);
-            framebuffer.point(x, y, fragment.depth);
+// Entorno del fondo y de los shaders de reflexión: el de --skybox o un cielo estrellado
+fn create_environment(config: &Config) -> Arc<CubeMap> {
+    let environment = match &config.skybox_path {
+        Some(path) => {
+            let environment = CubeMap::load(path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                process::exit(1);
+            });
+            println!("Skybox cargado: caras de {}x{}", environment.face_size, environment.face_size);
+            environment
         }
+        None => CubeMap::starfield(512, 1),
+    };
+    Arc::new(environment)
+}
+
+fn load_model(path: &str) -> Model {
+    let mut model = Model::load_from_file(path)
+        .unwrap_or_else(|e| {
+            eprintln!("No se pudo cargar el archivo OBJ '{}': {}", path, e);
+            process::exit(1);
+        });
+    model.normalize_and_center(1.5);
+    model
+}
+
+fn load_scene(config: &Config, registry: &ShaderRegistry) -> Scene {
+    let scene = match &config.scene_path {
+        Some(path) => Scene::load_from_file(path, registry).unwrap_or_else(|e| {
+            eprintln!("No se pudo cargar la escena '{}': {}", path, e);
+            process::exit(1);
+        }),
+        None => Scene::from_model(load_model(&config.model_path)),
+    };
+
+    println!("Escena cargada:");
+    println!("  Modelos: {}", scene.models.len());
+    println!("  Nodos: {}", scene.node_count());
+    for model in &scene.models {
+        println!("  Vértices únicos: {}, triángulos: {}", model.vertices.len(), model.triangle_count());
     }
+
+    scene
 }
 
-fn main() {
+fn run_headless(config: &Config, scene: &Scene, shader: &ShaderProgram, lights: &[Light], environment: &CubeMap) {
+    let mut framebuffer = Framebuffer::new_with_samples(config.width, config.height, config.msaa_samples);
+    framebuffer.set_background_color(Color::new(20, 20, 40));
+    framebuffer.set_tone_mapping(config.tone_mapping, config.exposure);
+
+    if let Err(e) = fs::create_dir_all(&config.output_dir) {
+        eprintln!("No se pudo crear la carpeta '{}': {}", config.output_dir, e);
+        process::exit(1);
+    }
+
+    let mut camera = default_camera();
+
+    // Las luces y la escena no cambian entre frames: las sombras se calculan una vez
+    let shadow_maps = if config.shadows {
+        render_shadow_maps(lights, scene, &config.shadow_settings)
+    } else {
+        Vec::new()
+    };
+
+    for frame in 0..config.frames {
+        // Tiempo fijo por frame para que los renders sean reproducibles
+        let time = frame as f32 / 60.0;
+
+        framebuffer.clear();
+        let uniforms = create_uniforms(&camera, lights, shadow_maps.clone(), config.width, config.height, time);
+        if config.skybox {
+            render_skybox(&mut framebuffer, &uniforms, environment);
+        }
+        render_scene(&mut framebuffer, &uniforms, scene, shader, &config.pipeline);
+
+        let path = Path::new(&config.output_dir).join(format!("frame_{:04}.{}", frame, config.image_format.extension()));
+        if let Err(e) = framebuffer.export(&path, config.image_format) {
+            eprintln!("No se pudo guardar '{}': {}", path.display(), e);
+            process::exit(1);
+        }
+        println!("Frame guardado: {}", path.display());
+
+        camera.orbit(config.orbit_step, 0.0);
+    }
+}
+
+fn save_screenshot(framebuffer: &Framebuffer, config: &Config) {
+    let path = Path::new(&config.output_dir).join(timestamped_filename("captura", config.image_format));
+    let result = fs::create_dir_all(&config.output_dir).and_then(|_| framebuffer.save(&path));
+
+    match result {
+        Ok(()) => println!("Captura guardada: {}", path.display()),
+        Err(e) => eprintln!("No se pudo guardar la captura '{}': {}", path.display(), e),
+    }
+}
+
+fn run_window(
+    config: &Config,
+    scene: &Scene,
+    registry: &ShaderRegistry,
+    shader: Arc<ShaderProgram>,
+    mut lights: Vec<Light>,
+    environment: &CubeMap,
+) {
     let mut window = Window::new(
-        "3D Renderer [WASD/Flechas: Cámara | 1-5: Shaders | R: Reset | ESC: Salir]",
-        SCREEN_WIDTH,
-        SCREEN_HEIGHT,
+        "3D Renderer [WASD/Flechas: Cámara | 0-9/N: Shaders | G/T: Luces | O: Sombras | U/-/=: Tone mapping | L: Modo | C/F: Culling | M: MSAA | R: Reset | P: Captura | ESC: Salir]",
+        config.width,
+        config.height,
         WindowOptions::default(),
     )
     .unwrap_or_else(|e| {
@@ -73,35 +285,44 @@ fn main() {
 
     window.set_target_fps(60);
 
-    let mut framebuffer = Framebuffer::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+    let mut framebuffer = Framebuffer::new_with_samples(config.width, config.height, config.msaa_samples);
     framebuffer.set_background_color(Color::new(20, 20, 40));
+    framebuffer.set_tone_mapping(config.tone_mapping, config.exposure);
 
-    let mut model = Model::load_from_file("spaceship.obj")
-        .expect("No se pudo cargar el archivo OBJ");
-    model.normalize_and_center(1.5);
-
-    println!("Modelo cargado:");
-    println!("  Vértices: {}", model.vertices.len());
     println!("\nControles:");
     println!("  W/S o ↑/↓: Orbitar verticalmente");
     println!("  A/D o ←/→: Orbitar horizontalmente");
     println!("  Q/E: Zoom");
-    println!("  1: Shader estático");
-    println!("  2: Shader difuso");
-    println!("  3: Cel Shading");
-    println!("  4: Shader procedural");
-    println!("  5: Normal Map");
+    for (i, program) in registry.iter().take(MAX_SHADER_KEYS).enumerate() {
+        println!("  {}: Shader {}", (i + 1) % 10, program.description);
+    }
+    println!("  N: Siguiente shader (incluye los que no tienen tecla)");
+    println!("  H: Mostrar/ocultar skybox");
+    println!("  C: Cambiar face culling (back/front/none)");
+    println!("  F: Invertir orden de la cara frontal (CCW/CW)");
+    println!("  L: Cambiar modo (sólido/wireframe/puntos/sólido+wireframe/hidden-line)");
+    println!("  K: Anti-aliasing de líneas");
+    println!("  Y: Transparencia ordenada / independiente del orden (A-buffer)");
+    println!("  M: Cambiar MSAA (1x/2x/4x/8x)");
+    println!("  G: Seleccionar siguiente luz");
+    println!("  T: Encender/apagar la luz seleccionada");
+    println!("  Z/X: Girar la luz seleccionada horizontalmente");
+    println!("  V/B: Girar la luz seleccionada verticalmente");
+    println!("  O: Activar/desactivar sombras");
+    println!("  U: Cambiar tone mapping (none/reinhard/aces/exposure)");
+    println!("  -/=: Bajar/subir la exposición");
     println!("  R: Resetear cámara");
+    println!("  P: Guardar captura");
     println!("  ESC: Salir");
 
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 0.0, 5.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
-    );
+    let mut camera = default_camera();
 
-    let mut rotation = Vec3::new(0.0, 0.0, 0.0);
-    let mut current_shader = "diffuse";
+    let mut current_shader = shader;
+    let mut pipeline = config.pipeline;
+    let mut selected_light = 0;
+    let mut show_skybox = config.skybox;
+    let mut shadows = config.shadows;
+    let mut shadow_cache = ShadowCache::default();
     let start_time = Instant::now();
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
@@ -127,59 +348,169 @@ fn main() {
             camera.zoom(0.1);
         }
 
-        // Selección de shaders
-        if window.is_key_pressed(Key::Key1, minifb::KeyRepeat::No) {
-            current_shader = "static_color";
-            println!("Shader: Estático");
+        // Selección de shaders: la tecla N activa el N-ésimo shader registrado
+        for (i, key) in SHADER_KEYS.iter().enumerate() {
+            if window.is_key_pressed(*key, minifb::KeyRepeat::No) {
+                if let Some(program) = registry.by_key(i + 1) {
+                    println!("Shader: {}", program.description);
+                    current_shader = program;
+                }
+            }
+        }
+
+        if window.is_key_pressed(Key::N, minifb::KeyRepeat::No) {
+            if let Some(program) = registry.next_after(&current_shader.name) {
+                println!("Shader: {}", program.description);
+                current_shader = program;
+            }
+        }
+
+        if window.is_key_pressed(Key::H, minifb::KeyRepeat::No) {
+            show_skybox = !show_skybox;
+            println!("Skybox: {}", if show_skybox { "sí" } else { "no" });
+        }
+
+        // Face culling
+        if window.is_key_pressed(Key::C, minifb::KeyRepeat::No) {
+            pipeline.cull_mode = pipeline.cull_mode.next();
+            println!("Culling: {:?}", pipeline.cull_mode);
+        }
+        if window.is_key_pressed(Key::F, minifb::KeyRepeat::No) {
+            pipeline.front_face = pipeline.front_face.flipped();
+            println!("Cara frontal: {:?}", pipeline.front_face);
+        }
+
+        // Modos de render
+        if window.is_key_pressed(Key::L, minifb::KeyRepeat::No) {
+            pipeline.render_mode = pipeline.render_mode.next();
+            println!("Modo de render: {:?}", pipeline.render_mode);
         }
-        if window.is_key_pressed(Key::Key2, minifb::KeyRepeat::No) {
-            current_shader = "diffuse";
-            println!("Shader: Difuso");
+        if window.is_key_pressed(Key::K, minifb::KeyRepeat::No) {
+            pipeline.line_antialiasing = !pipeline.line_antialiasing;
+            println!("Anti-aliasing de líneas: {}", if pipeline.line_antialiasing { "sí" } else { "no" });
         }
-        if window.is_key_pressed(Key::Key3, minifb::KeyRepeat::No) {
-            current_shader = "cel_shading";
-            println!("Shader: Cel Shading");
+
+        // Transparencias
+        if window.is_key_pressed(Key::Y, minifb::KeyRepeat::No) {
+            pipeline.transparency = pipeline.transparency.next();
+            println!("Transparencia: {:?}", pipeline.transparency);
         }
-        if window.is_key_pressed(Key::Key4, minifb::KeyRepeat::No) {
-            current_shader = "procedural";
-            println!("Shader: Procedural");
+
+        // Anti-aliasing: 1x -> 2x -> 4x -> 8x
+        if window.is_key_pressed(Key::M, minifb::KeyRepeat::No) {
+            let samples = if framebuffer.samples >= 8 { 1 } else { framebuffer.samples * 2 };
+            framebuffer.set_samples(samples);
+            println!("MSAA: {}x", framebuffer.samples);
         }
-        if window.is_key_pressed(Key::Key5, minifb::KeyRepeat::No) {
-            current_shader = "normal_map";
-            println!("Shader: Normal Map");
+
+        // Luces: selección, encendido y rotación alrededor del origen
+        if !lights.is_empty() {
+            if window.is_key_pressed(Key::G, minifb::KeyRepeat::No) {
+                selected_light = (selected_light + 1) % lights.len();
+                let light = &lights[selected_light];
+                println!("Luz seleccionada: {} ({})", light.name, if light.enabled { "encendida" } else { "apagada" });
+            }
+            let light = &mut lights[selected_light];
+            if window.is_key_pressed(Key::T, minifb::KeyRepeat::No) {
+                light.enabled = !light.enabled;
+                println!("Luz {}: {}", light.name, if light.enabled { "encendida" } else { "apagada" });
+            }
+            if window.is_key_down(Key::Z) {
+                light.orbit(-0.05, 0.0);
+            }
+            if window.is_key_down(Key::X) {
+                light.orbit(0.05, 0.0);
+            }
+            if window.is_key_down(Key::V) {
+                light.orbit(0.0, 0.05);
+            }
+            if window.is_key_down(Key::B) {
+                light.orbit(0.0, -0.05);
+            }
+        }
+
+        if window.is_key_pressed(Key::O, minifb::KeyRepeat::No) {
+            shadows = !shadows;
+            println!("Sombras: {}", if shadows { "sí" } else { "no" });
+        }
+
+        // Tone mapping y exposición (pasos de 1/4 de stop)
+        if window.is_key_pressed(Key::U, minifb::KeyRepeat::No) {
+            framebuffer.tone_mapping = framebuffer.tone_mapping.next();
+            println!("Tone mapping: {:?}", framebuffer.tone_mapping);
+        }
+        if window.is_key_pressed(Key::Minus, minifb::KeyRepeat::Yes) {
+            framebuffer.exposure /= 2f32.powf(0.25);
+            println!("Exposición: {:.2}", framebuffer.exposure);
+        }
+        if window.is_key_pressed(Key::Equal, minifb::KeyRepeat::Yes) {
+            framebuffer.exposure *= 2f32.powf(0.25);
+            println!("Exposición: {:.2}", framebuffer.exposure);
         }
 
         // Reset cámara
         if window.is_key_pressed(Key::R, minifb::KeyRepeat::No) {
-            camera = Camera::new(
-                Vec3::new(0.0, 0.0, 5.0),
-                Vec3::new(0.0, 0.0, 0.0),
-                Vec3::new(0.0, 1.0, 0.0),
-            );
-            rotation = Vec3::new(0.0, 0.0, 0.0);
+            camera = default_camera();
             println!("Cámara reseteada");
         }
 
+        let take_screenshot = window.is_key_pressed(Key::P, minifb::KeyRepeat::No);
+
         framebuffer.clear();
 
-        let model_matrix = create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 1.0, rotation);
-        let view_matrix = camera.get_view_matrix();
-        let projection_matrix = camera.get_projection_matrix(SCREEN_WIDTH as f32 / SCREEN_HEIGHT as f32);
-        let viewport_matrix = create_viewport_matrix(SCREEN_WIDTH as f32, SCREEN_HEIGHT as f32);
-
-        let uniforms = Uniforms {
-            model_matrix,
-            view_matrix,
-            projection_matrix,
-            viewport_matrix,
-            time,
-            light_dir: Vec3::new(0.5, -0.5, -1.0),
+        // Los shadow maps solo se rehacen cuando cambia alguna luz
+        let shadow_maps = if shadows {
+            shadow_cache.update(&lights, scene, &config.shadow_settings)
+        } else {
+            Vec::new()
         };
+        let uniforms = create_uniforms(&camera, &lights, shadow_maps, config.width, config.height, time);
 
-        render(&mut framebuffer, &uniforms, &model.vertices, current_shader);
+        if show_skybox {
+            render_skybox(&mut framebuffer, &uniforms, environment);
+        }
+        render_scene(&mut framebuffer, &uniforms, scene, &current_shader, &pipeline);
+
+        if take_screenshot {
+            save_screenshot(&framebuffer, config);
+        }
 
         window
-            .update_with_buffer(&framebuffer.buffer, SCREEN_WIDTH, SCREEN_HEIGHT)
+            .update_with_buffer(&framebuffer.buffer, config.width, config.height)
             .unwrap();
     }
-}
\ No newline at end of file
+}
+
+fn main() {
+    let config = Config::from_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+
+    if let Some(threads) = config.threads {
+        if let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(threads).build_global() {
+            eprintln!("No se pudo configurar el número de hilos: {}", e);
+        }
+    }
+
+    let environment = create_environment(&config);
+
+    let registry = ShaderRegistry::with_builtin(BuiltinResources {
+        pbr: create_pbr_shader(&config),
+        texture: create_texture_shader(&config),
+        normal_mapped: create_normal_mapped_shader(&config),
+        environment: environment.clone(),
+    });
+    let shader = registry.lookup(&config.shader).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        process::exit(2);
+    });
+    let scene = load_scene(&config, &registry);
+    let lights = create_lights(&config);
+
+    if config.headless {
+        run_headless(&config, &scene, &shader, &lights, &environment);
+    } else {
+        run_window(&config, &scene, &registry, shader, lights, &environment);
+    }
+}
@@ -0,0 +1,43 @@
+use crate::color::Color;
+
+// Parámetros de superficie para los modelos de iluminación Phong y Blinn-Phong.
+// La opacidad es el canal alfa del color difuso.
+#[derive(Debug, Clone, Copy)]
+pub struct Material {
+    pub ambient: Color,
+    pub diffuse: Color,
+    pub specular: Color,
+    // Exponente especular: valores altos dan brillos más pequeños y definidos
+    pub shininess: f32,
+}
+
+impl Material {
+    pub fn new(ambient: Color, diffuse: Color, specular: Color, shininess: f32) -> Self {
+        Material { ambient, diffuse, specular, shininess }
+    }
+
+    // Material con ambiente proporcional al color difuso
+    pub fn from_color(diffuse: Color, specular: Color, shininess: f32) -> Self {
+        Material::new(diffuse * 0.3, diffuse, specular, shininess)
+    }
+
+    pub fn plastic(color: Color) -> Self {
+        Material::from_color(color, Color::new(200, 200, 200), 32.0)
+    }
+
+    // Vidrio de color con brillos nítidos; dibujar con una mezcla transparente
+    pub fn glass(color: Color, opacity: f32) -> Self {
+        let alpha = (opacity.clamp(0.0, 1.0) * 255.0).round() as u8;
+        Material::from_color(color.with_alpha(alpha), Color::white(), 96.0)
+    }
+
+    pub fn opacity(&self) -> f32 {
+        self.diffuse.alpha()
+    }
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::plastic(Color::new(255, 200, 100))
+    }
+}
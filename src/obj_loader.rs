@@ -1,10 +1,14 @@
-use nalgebra_glm::{Vec2, Vec3};
+use nalgebra_glm::{cross, dot, Vec2, Vec3, Vec4};
 use crate::vertex::Vertex;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
+// Malla indexada: `vertices` contiene cada combinación v/vt/vn una sola vez y
+// `indices` forma los triángulos de tres en tres
 pub struct Model {
     pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
 }
 
 impl Model {
@@ -17,6 +21,8 @@ impl Model {
         let mut temp_normals = Vec::new();
         let mut temp_texcoords = Vec::new();
         let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let mut unique_vertices: HashMap<(usize, usize, usize), u32> = HashMap::new();
 
         for line in reader.lines() {
             let line = line.map_err(|e| format!("Error leyendo línea: {}", e))?;
@@ -28,67 +34,159 @@ impl Model {
 
             let parts: Vec<&str> = line.split_whitespace().collect();
 
-            match parts.get(0) {
-                Some(&"v") => {
-                    if parts.len() >= 4 {
-                        let x: f32 = parts[1].parse().unwrap_or(0.0);
-                        let y: f32 = parts[2].parse().unwrap_or(0.0);
-                        let z: f32 = parts[3].parse().unwrap_or(0.0);
-                        temp_positions.push(Vec3::new(x, y, z));
-                    }
+            match parts.first() {
+                Some(&"v") if parts.len() >= 4 => {
+                    let x: f32 = parts[1].parse().unwrap_or(0.0);
+                    let y: f32 = parts[2].parse().unwrap_or(0.0);
+                    let z: f32 = parts[3].parse().unwrap_or(0.0);
+                    temp_positions.push(Vec3::new(x, y, z));
                 }
-                Some(&"vn") => {
-                    if parts.len() >= 4 {
-                        let x: f32 = parts[1].parse().unwrap_or(0.0);
-                        let y: f32 = parts[2].parse().unwrap_or(0.0);
-                        let z: f32 = parts[3].parse().unwrap_or(0.0);
-                        temp_normals.push(Vec3::new(x, y, z));
-                    }
+                Some(&"vn") if parts.len() >= 4 => {
+                    let x: f32 = parts[1].parse().unwrap_or(0.0);
+                    let y: f32 = parts[2].parse().unwrap_or(0.0);
+                    let z: f32 = parts[3].parse().unwrap_or(0.0);
+                    temp_normals.push(Vec3::new(x, y, z));
                 }
-                Some(&"vt") => {
-                    if parts.len() >= 3 {
-                        let u: f32 = parts[1].parse().unwrap_or(0.0);
-                        let v: f32 = parts[2].parse().unwrap_or(0.0);
-                        temp_texcoords.push(Vec2::new(u, v));
-                    }
+                Some(&"vt") if parts.len() >= 3 => {
+                    let u: f32 = parts[1].parse().unwrap_or(0.0);
+                    let v: f32 = parts[2].parse().unwrap_or(0.0);
+                    temp_texcoords.push(Vec2::new(u, v));
                 }
                 Some(&"f") => {
-                    let mut face_vertices = Vec::new();
+                    let mut face_indices = Vec::new();
 
-                    for i in 1..parts.len() {
-                        let indices: Vec<&str> = parts[i].split('/').collect();
+                    for part in parts.iter().skip(1) {
+                        let fields: Vec<&str> = part.split('/').collect();
 
-                        let pos_idx: usize = indices[0].parse::<usize>().unwrap_or(1) - 1;
-                        let tex_idx: usize = if indices.len() > 1 && !indices[1].is_empty() {
-                            indices[1].parse::<usize>().unwrap_or(1) - 1
+                        let pos_idx: usize = fields[0].parse::<usize>().unwrap_or(1) - 1;
+                        let tex_idx: usize = if fields.len() > 1 && !fields[1].is_empty() {
+                            fields[1].parse::<usize>().unwrap_or(1) - 1
                         } else {
                             0
                         };
-                        let norm_idx: usize = if indices.len() > 2 {
-                            indices[2].parse::<usize>().unwrap_or(1) - 1
+                        let norm_idx: usize = if fields.len() > 2 {
+                            fields[2].parse::<usize>().unwrap_or(1) - 1
                         } else {
                             0
                         };
 
-                        let position = temp_positions.get(pos_idx).cloned().unwrap_or(Vec3::zeros());
-                        let normal = temp_normals.get(norm_idx).cloned().unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                        let tex_coords = temp_texcoords.get(tex_idx).cloned().unwrap_or(Vec2::zeros());
+                        let key = (pos_idx, tex_idx, norm_idx);
+                        let index = *unique_vertices.entry(key).or_insert_with(|| {
+                            let position = temp_positions.get(pos_idx).cloned().unwrap_or(Vec3::zeros());
+                            let normal = temp_normals.get(norm_idx).cloned().unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+                            let tex_coords = temp_texcoords.get(tex_idx).cloned().unwrap_or(Vec2::zeros());
+
+                            vertices.push(Vertex::new(position, normal, tex_coords));
+                            (vertices.len() - 1) as u32
+                        });
 
-                        face_vertices.push(Vertex::new(position, normal, tex_coords));
+                        face_indices.push(index);
                     }
 
                     // Triangulación en abanico
-                    for i in 1..(face_vertices.len() - 1) {
-                        vertices.push(face_vertices[0].clone());
-                        vertices.push(face_vertices[i].clone());
-                        vertices.push(face_vertices[i + 1].clone());
+                    for i in 1..face_indices.len().saturating_sub(1) {
+                        indices.push(face_indices[0]);
+                        indices.push(face_indices[i]);
+                        indices.push(face_indices[i + 1]);
                     }
                 }
                 _ => {}
             }
         }
 
-        Ok(Model { vertices })
+        let mut model = Model { vertices, indices };
+        model.generate_tangents();
+        Ok(model)
+    }
+
+    // Tangentes por vértice compatibles con MikkTSpace (la convención de Blender
+    // al hornear normal maps): cada triángulo aporta su tangente y bitangente
+    // según sus UV, ortogonalizadas respecto a la normal del vértice,
+    // normalizadas y ponderadas por el ángulo de la esquina, así los triángulos
+    // grandes o con UV muy estiradas no dominan la suma. El signo de la
+    // bitangente va en w.
+    // Los vértices compartidos por triángulos con UV espejadas se duplican para
+    // que cada lado conserve su propia orientación.
+    pub fn generate_tangents(&mut self) {
+        let vertex_count = self.vertices.len();
+        let mut tangents = vec![Vec3::zeros(); vertex_count];
+        let mut bitangents = vec![Vec3::zeros(); vertex_count];
+        // Orientación de las UV vista por cada vértice: 0 sin asignar, ±1 después
+        let mut orientation = vec![0.0f32; vertex_count];
+        let mut mirrored_copies: HashMap<u32, u32> = HashMap::new();
+
+        for triangle in 0..self.triangle_count() {
+            let corners = [0, 1, 2].map(|i| self.indices[triangle * 3 + i] as usize);
+            let [p0, p1, p2] = corners.map(|i| self.vertices[i].position);
+            let [uv0, uv1, uv2] = corners.map(|i| self.vertices[i].tex_coords);
+
+            let (edge1, edge2) = (p1 - p0, p2 - p0);
+            let (duv1, duv2) = (uv1 - uv0, uv2 - uv0);
+            let det = duv1.x * duv2.y - duv2.x * duv1.y;
+            if det.abs() < 1e-12 {
+                // UV degeneradas: el triángulo no define una dirección de tangente
+                continue;
+            }
+
+            let tangent = (edge1 * duv2.y - edge2 * duv1.y) / det;
+            let bitangent = (edge2 * duv1.x - edge1 * duv2.x) / det;
+            let sign = det.signum();
+
+            for (corner, &vertex) in corners.iter().enumerate() {
+                let mut vertex = vertex;
+                if orientation[vertex] == 0.0 {
+                    orientation[vertex] = sign;
+                } else if orientation[vertex] != sign {
+                    let copy = *mirrored_copies.entry(vertex as u32).or_insert_with(|| {
+                        self.vertices.push(self.vertices[vertex].clone());
+                        tangents.push(Vec3::zeros());
+                        bitangents.push(Vec3::zeros());
+                        orientation.push(sign);
+                        (self.vertices.len() - 1) as u32
+                    });
+                    self.indices[triangle * 3 + corner] = copy;
+                    vertex = copy as usize;
+                }
+
+                // Ángulo de la esquina entre las dos aristas que salen del vértice
+                let position = self.vertices[corners[corner]].position;
+                let a = self.vertices[corners[(corner + 1) % 3]].position - position;
+                let b = self.vertices[corners[(corner + 2) % 3]].position - position;
+                let angle = if a.magnitude() > 0.0 && b.magnitude() > 0.0 {
+                    dot(&a.normalize(), &b.normalize()).clamp(-1.0, 1.0).acos()
+                } else {
+                    0.0
+                };
+
+                // Como MikkTSpace: la tangente de la cara se proyecta sobre el plano
+                // de la normal del vértice y se normaliza antes de acumularse
+                let normal = self.vertices[vertex].normal.normalize();
+                let project = |v: Vec3| {
+                    let projected = v - normal * dot(&normal, &v);
+                    if projected.magnitude() > 1e-8 { projected.normalize() } else { Vec3::zeros() }
+                };
+
+                tangents[vertex] += project(tangent) * angle;
+                bitangents[vertex] += project(bitangent) * angle;
+            }
+        }
+
+        for (i, vertex) in self.vertices.iter_mut().enumerate() {
+            let normal = vertex.normal.normalize();
+            // Gram-Schmidt: se quita la componente en la dirección de la normal
+            let mut tangent = tangents[i] - normal * dot(&normal, &tangents[i]);
+            if tangent.magnitude() < 1e-8 {
+                tangent = any_perpendicular(&normal);
+            }
+            let tangent = tangent.normalize();
+            let sign = if dot(&cross(&normal, &tangent), &bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+
+            vertex.tangent = Vec4::new(tangent.x, tangent.y, tangent.z, sign);
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
     }
 
     pub fn get_bounds(&self) -> (Vec3, Vec3) {
@@ -128,4 +226,122 @@ impl Model {
             vertex.position = (vertex.position - center) * scale_factor;
         }
     }
-}
\ No newline at end of file
+}
+
+// Un vector unitario cualquiera perpendicular a `normal`
+fn any_perpendicular(normal: &Vec3) -> Vec3 {
+    let axis = if normal.x.abs() < 0.9 { Vec3::new(1.0, 0.0, 0.0) } else { Vec3::new(0.0, 1.0, 0.0) };
+    cross(normal, &axis).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Escribe el OBJ en un archivo temporal propio del test y lo carga
+    fn load(name: &str, source: &str) -> Model {
+        let path = std::env::temp_dir().join(format!("proyecto2_{}_{}.obj", name, std::process::id()));
+        std::fs::write(&path, source).unwrap();
+        let model = Model::load_from_file(path.to_str().unwrap());
+        std::fs::remove_file(&path).unwrap();
+        model.unwrap()
+    }
+
+    const QUAD: &str = "\
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+vn 0 0 1
+f 1/1/1 2/2/1 3/3/1
+f 1/1/1 3/3/1 4/4/1
+";
+
+    #[test]
+    fn shared_vertices_are_loaded_once() {
+        let model = load("shared", QUAD);
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(model.triangle_count(), 2);
+        assert_eq!(model.vertices[2].tex_coords, Vec2::new(1.0, 1.0));
+    }
+
+    #[test]
+    fn same_position_with_different_attributes_is_split() {
+        // La posición 1 aparece con dos UV distintas, como en una costura
+        let model = load("seam", "v 0 0 0\nv 1 0 0\nv 0 1 0\nvt 0 0\nvt 1 0\nvt 0 1\nvt 0.5 0.5\nf 1/1 2/2 3/3\nf 1/4 3/3 2/2\n");
+        assert_eq!(model.vertices.len(), 4);
+        assert_eq!(model.indices, vec![0, 1, 2, 3, 2, 1]);
+    }
+
+    #[test]
+    fn polygons_are_fan_triangulated() {
+        let model = load("fan", "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nv -1 1 0\nf 1 2 3 4 5\n");
+        assert_eq!(model.vertices.len(), 5);
+        assert_eq!(model.indices, vec![0, 1, 2, 0, 2, 3, 0, 3, 4]);
+    }
+
+    #[test]
+    fn missing_file_is_an_error() {
+        assert!(Model::load_from_file("no/existe.obj").is_err());
+    }
+
+    fn assert_tangent(vertex: &Vertex, expected: Vec4) {
+        assert!((vertex.tangent - expected).magnitude() < 1e-5, "{:?} != {:?}", vertex.tangent, expected);
+    }
+
+    #[test]
+    fn quad_tangent_follows_u() {
+        let model = load("tangent", QUAD);
+        for vertex in &model.vertices {
+            assert_tangent(vertex, Vec4::new(1.0, 0.0, 0.0, 1.0));
+        }
+
+        // UV giradas 90°: u crece hacia +y
+        let rotated = QUAD.replace("vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1", "vt 0 1\nvt 0 0\nvt 1 0\nvt 1 1");
+        for vertex in &load("rotated", &rotated).vertices {
+            assert_tangent(vertex, Vec4::new(0.0, 1.0, 0.0, 1.0));
+        }
+
+        // u invertida: la tangente apunta a -x y la bitangente queda espejada
+        let flipped = QUAD.replace("vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1", "vt 1 0\nvt 0 0\nvt 0 1\nvt 1 1");
+        for vertex in &load("flipped", &flipped).vertices {
+            assert_tangent(vertex, Vec4::new(-1.0, 0.0, 0.0, -1.0));
+        }
+    }
+
+    #[test]
+    fn mirrored_uv_seam_splits_shared_vertices() {
+        // Dos quads con la arista x = 1 compartida; el de la derecha tiene las
+        // UV espejadas, como un modelo simétrico que reutiliza la textura
+        let model = load(
+            "mirror",
+            "v 0 0 0\nv 1 0 0\nv 2 0 0\nv 0 1 0\nv 1 1 0\nv 2 1 0\n\
+             vt 0 0\nvt 1 0\nvt 0 1\nvt 1 1\nvn 0 0 1\n\
+             f 1/1/1 2/2/1 5/4/1\nf 1/1/1 5/4/1 4/3/1\n\
+             f 2/2/1 3/1/1 6/3/1\nf 2/2/1 6/3/1 5/4/1\n",
+        );
+
+        // Seis combinaciones únicas más una copia por cada vértice de la costura
+        assert_eq!(model.vertices.len(), 8);
+        for (triangle, corners) in model.indices.chunks(3).enumerate() {
+            let expected = if triangle < 2 { Vec4::new(1.0, 0.0, 0.0, 1.0) } else { Vec4::new(-1.0, 0.0, 0.0, -1.0) };
+            for &index in corners {
+                assert_tangent(&model.vertices[index as usize], expected);
+            }
+        }
+    }
+
+    #[test]
+    fn degenerate_uvs_give_a_perpendicular_tangent() {
+        let model = load("degenerate", "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1\n");
+        for vertex in &model.vertices {
+            assert!(vertex.tangent.z.abs() < 1e-6);
+            assert!((vertex.tangent.xyz().magnitude() - 1.0).abs() < 1e-5);
+        }
+    }
+}
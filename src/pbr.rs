@@ -0,0 +1,169 @@
+use nalgebra_glm::{dot, Vec3, Vec4};
+use std::f32::consts::PI;
+use std::sync::Arc;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::shaders::{FragmentShader, Uniforms};
+use crate::texture::Texture;
+
+// Reflectancia a incidencia normal de los dieléctricos (4%)
+const DIELECTRIC_F0: f32 = 0.04;
+// Rugosidad mínima: con 0 la distribución GGX se vuelve un delta y produce NaN
+const MIN_ROUGHNESS: f32 = 0.045;
+
+// Parámetros metallic/roughness al estilo de Blender (Principled BSDF) y glTF.
+// Cada mapa, si existe, multiplica al valor constante correspondiente.
+#[derive(Clone)]
+pub struct PbrMaterial {
+    // Color base en sRGB; su canal alfa es la opacidad
+    pub albedo: Color,
+    pub metallic: f32,
+    pub roughness: f32,
+    pub ao: f32,
+    pub albedo_map: Option<Arc<Texture>>,
+    // Convención glTF: rugosidad en el canal G y metalicidad en el B
+    pub metallic_roughness_map: Option<Arc<Texture>>,
+    // Oclusión ambiental en el canal R
+    pub ao_map: Option<Arc<Texture>>,
+}
+
+impl PbrMaterial {
+    pub fn new(albedo: Color, metallic: f32, roughness: f32) -> Self {
+        PbrMaterial {
+            albedo,
+            metallic: metallic.clamp(0.0, 1.0),
+            roughness: roughness.clamp(0.0, 1.0),
+            ao: 1.0,
+            albedo_map: None,
+            metallic_roughness_map: None,
+            ao_map: None,
+        }
+    }
+
+    pub fn with_albedo_map(mut self, texture: Arc<Texture>) -> Self {
+        self.albedo_map = Some(texture);
+        self
+    }
+
+    pub fn with_metallic_roughness_map(mut self, texture: Arc<Texture>) -> Self {
+        self.metallic_roughness_map = Some(texture);
+        self
+    }
+
+    pub fn with_ao_map(mut self, texture: Arc<Texture>) -> Self {
+        self.ao_map = Some(texture);
+        self
+    }
+
+    // Valores efectivos en un fragmento: (albedo lineal, opacidad, metallic, roughness, ao)
+    fn evaluate(&self, fragment: &Fragment) -> (Vec3, f32, f32, f32, f32) {
+        let mut albedo = self.albedo.to_linear();
+        let mut opacity = self.albedo.alpha();
+        let mut metallic = self.metallic;
+        let mut roughness = self.roughness;
+        let mut ao = self.ao;
+
+        if let Some(map) = &self.albedo_map {
+            let texel = map.sample_fragment(fragment);
+            albedo = albedo.component_mul(&texel.to_linear());
+            opacity *= texel.alpha();
+        }
+        if let Some(map) = &self.metallic_roughness_map {
+            let texel = map.sample_fragment(fragment);
+            roughness *= texel.g as f32 / 255.0;
+            metallic *= texel.b as f32 / 255.0;
+        }
+        if let Some(map) = &self.ao_map {
+            ao *= map.sample_fragment(fragment).r as f32 / 255.0;
+        }
+
+        (albedo, opacity, metallic, roughness.max(MIN_ROUGHNESS), ao)
+    }
+}
+
+impl Default for PbrMaterial {
+    // Valores por defecto del Principled BSDF de Blender
+    fn default() -> Self {
+        PbrMaterial::new(Color::new(204, 204, 204), 0.0, 0.5)
+    }
+}
+
+// BRDF de Cook-Torrance con distribución GGX, geometría de Smith (Schlick-GGX)
+// y Fresnel de Schlick, iluminada por las luces de `Uniforms`. La radiancia de
+// cada luz se multiplica por π para que una intensidad de 1 equivalga a la
+// "Strength" 1 de un Sun de Blender (un difuso blanco de frente da 1.0).
+pub struct PbrShader {
+    pub material: PbrMaterial,
+    // Luz ambiente uniforme, atenuada por la oclusión ambiental
+    pub ambient_intensity: f32,
+}
+
+impl PbrShader {
+    pub fn new(material: PbrMaterial) -> Self {
+        PbrShader { material, ambient_intensity: 0.03 }
+    }
+}
+
+impl FragmentShader for PbrShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        Color::from_linear_rgba(self.shade_hdr(fragment, uniforms))
+    }
+
+    // Radiancia lineal; el tone mapping y la codificación sRGB los hace el framebuffer
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        let (albedo, opacity, metallic, roughness, ao) = self.material.evaluate(fragment);
+
+        let n = fragment.normal.normalize();
+        let v = fragment.view_dir(&uniforms.camera_position);
+        let n_dot_v = dot(&n, &v).max(1e-4);
+
+        // Los metales no tienen difuso y tiñen el reflejo especular con su albedo
+        let f0 = Vec3::repeat(DIELECTRIC_F0).lerp(&albedo, metallic);
+
+        let mut color = albedo * self.ambient_intensity * ao;
+
+        for light in uniforms.lights_at(&fragment.world_position, &n) {
+            let l = light.direction;
+            let n_dot_l = dot(&n, &l).max(0.0);
+            if n_dot_l <= 0.0 {
+                continue;
+            }
+            let h = (v + l).normalize();
+
+            let d = distribution_ggx(dot(&n, &h).max(0.0), roughness);
+            let g = geometry_smith(n_dot_v, n_dot_l, roughness);
+            let f = fresnel_schlick(dot(&h, &v).max(0.0), f0);
+
+            let specular = f * (d * g / (4.0 * n_dot_v * n_dot_l));
+            // La energía que no se refleja se difunde (salvo en metales)
+            let k_d = (Vec3::repeat(1.0) - f) * (1.0 - metallic);
+            let diffuse = k_d.component_mul(&albedo) / PI;
+
+            color += (diffuse + specular).component_mul(&light.radiance) * (PI * n_dot_l);
+        }
+
+        color.push(opacity)
+    }
+}
+
+// Distribución de microfacetas de Trowbridge-Reitz (GGX), con alpha = roughness²
+pub fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let alpha = roughness * roughness;
+    let alpha2 = alpha * alpha;
+    let denom = n_dot_h * n_dot_h * (alpha2 - 1.0) + 1.0;
+    alpha2 / (PI * denom * denom)
+}
+
+// Término de sombreado/enmascarado de Smith con Schlick-GGX para luz directa
+pub fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let r = roughness + 1.0;
+    let k = r * r / 8.0;
+    let schlick = |n_dot_x: f32| n_dot_x / (n_dot_x * (1.0 - k) + k);
+    schlick(n_dot_v) * schlick(n_dot_l)
+}
+
+// Aproximación de Schlick para la reflectancia de Fresnel
+pub fn fresnel_schlick(cos_theta: f32, f0: Vec3) -> Vec3 {
+    let factor = (1.0 - cos_theta).clamp(0.0, 1.0).powi(5);
+    f0 + (Vec3::repeat(1.0) - f0) * factor
+}
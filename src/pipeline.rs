@@ -0,0 +1,280 @@
+use nalgebra_glm::{Vec3, Vec4};
+use crate::stencil::StencilState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CullMode {
+    None,
+    Back,
+    Front,
+}
+
+impl CullMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(CullMode::None),
+            "back" => Some(CullMode::Back),
+            "front" => Some(CullMode::Front),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            CullMode::None => CullMode::Back,
+            CullMode::Back => CullMode::Front,
+            CullMode::Front => CullMode::None,
+        }
+    }
+}
+
+// Orden de los vértices (visto desde la cámara) que define la cara frontal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrontFace {
+    CounterClockwise,
+    Clockwise,
+}
+
+impl FrontFace {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "ccw" => Some(FrontFace::CounterClockwise),
+            "cw" => Some(FrontFace::Clockwise),
+            _ => None,
+        }
+    }
+
+    pub fn flipped(self) -> Self {
+        match self {
+            FrontFace::CounterClockwise => FrontFace::Clockwise,
+            FrontFace::Clockwise => FrontFace::CounterClockwise,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderMode {
+    Solid,
+    Wireframe,
+    Points,
+    SolidWireframe,
+    HiddenLine,
+}
+
+impl RenderMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "solid" => Some(RenderMode::Solid),
+            "wireframe" => Some(RenderMode::Wireframe),
+            "points" => Some(RenderMode::Points),
+            "solid-wireframe" => Some(RenderMode::SolidWireframe),
+            "hidden-line" => Some(RenderMode::HiddenLine),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            RenderMode::Solid => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::Points,
+            RenderMode::Points => RenderMode::SolidWireframe,
+            RenderMode::SolidWireframe => RenderMode::HiddenLine,
+            RenderMode::HiddenLine => RenderMode::Solid,
+        }
+    }
+}
+
+// Ecuación con la que el color de un fragmento se combina con el que ya está
+// en el framebuffer. Todas operan sobre radiancia lineal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlendMode {
+    // Reemplaza el color; la opacidad se ignora
+    Opaque,
+    // src * a + dst * (1 - a): vidrio, cabinas, humo
+    Alpha,
+    // dst + src * a: brillos de motores, escudos de energía
+    Additive,
+    // dst * mix(1, src, a): vidrios de color que solo filtran la luz
+    Multiply,
+    // src + dst * (1 - a), con el color ya multiplicado por la opacidad
+    Premultiplied,
+}
+
+impl BlendMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "opaque" => Some(BlendMode::Opaque),
+            "alpha" => Some(BlendMode::Alpha),
+            "additive" => Some(BlendMode::Additive),
+            "multiply" => Some(BlendMode::Multiply),
+            "premultiplied" => Some(BlendMode::Premultiplied),
+            _ => None,
+        }
+    }
+
+    pub fn is_transparent(self) -> bool {
+        self != BlendMode::Opaque
+    }
+
+    // `src` es el color lineal del fragmento con la opacidad en `w`
+    pub fn blend(self, src: Vec4, dst: Vec3) -> Vec3 {
+        let (color, alpha) = (src.xyz(), src.w.clamp(0.0, 1.0));
+        match self {
+            BlendMode::Opaque => color,
+            BlendMode::Alpha => color * alpha + dst * (1.0 - alpha),
+            BlendMode::Additive => dst + color * alpha,
+            BlendMode::Multiply => dst.component_mul(&Vec3::repeat(1.0).lerp(&color, alpha)),
+            BlendMode::Premultiplied => color + dst * (1.0 - alpha),
+        }
+    }
+}
+
+// Cómo escribe un material en el framebuffer: ecuación de mezcla, si
+// actualiza el z-buffer y el color, y el stencil test. Los transparentes no
+// suelen escribir profundidad para que las superficies transparentes de atrás
+// sigan viéndose.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderState {
+    pub blend_mode: BlendMode,
+    pub depth_write: bool,
+    // Sin escritura de color el material solo afecta al z-buffer y al stencil
+    pub color_write: bool,
+    pub stencil: StencilState,
+}
+
+impl RenderState {
+    pub fn opaque() -> Self {
+        RenderState::blended(BlendMode::Opaque)
+    }
+
+    // Sin escritura de profundidad, que es lo habitual para transparentes
+    pub fn blended(blend_mode: BlendMode) -> Self {
+        RenderState {
+            blend_mode,
+            depth_write: !blend_mode.is_transparent(),
+            color_write: true,
+            stencil: StencilState::disabled(),
+        }
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.blend_mode.is_transparent()
+    }
+}
+
+impl Default for RenderState {
+    fn default() -> Self {
+        RenderState::opaque()
+    }
+}
+
+// Cómo se resuelve el orden de las superficies transparentes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransparencyMode {
+    // Se mezclan al dibujarse, ordenadas por objeto y por centroide de triángulo
+    Sorted,
+    // A-buffer: cada píxel guarda todos sus fragmentos transparentes y se
+    // mezclan ordenados por profundidad al resolver el frame
+    OrderIndependent,
+}
+
+impl TransparencyMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sorted" => Some(TransparencyMode::Sorted),
+            "oit" => Some(TransparencyMode::OrderIndependent),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            TransparencyMode::Sorted => TransparencyMode::OrderIndependent,
+            TransparencyMode::OrderIndependent => TransparencyMode::Sorted,
+        }
+    }
+}
+
+// Estado fijo del pipeline que no pertenece a los shaders
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineState {
+    pub cull_mode: CullMode,
+    pub front_face: FrontFace,
+    pub render_mode: RenderMode,
+    pub line_antialiasing: bool,
+    pub transparency: TransparencyMode,
+}
+
+impl Default for PipelineState {
+    fn default() -> Self {
+        PipelineState {
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::CounterClockwise,
+            render_mode: RenderMode::Solid,
+            line_antialiasing: false,
+            transparency: TransparencyMode::Sorted,
+        }
+    }
+}
+
+impl PipelineState {
+    // `signed_area` es el área con signo en coordenadas de pantalla (y hacia abajo),
+    // por lo que un triángulo antihorario visto desde la cámara tiene área negativa.
+    pub fn is_culled(&self, signed_area: f32) -> bool {
+        let counter_clockwise = signed_area < 0.0;
+        let front_facing = match self.front_face {
+            FrontFace::CounterClockwise => counter_clockwise,
+            FrontFace::Clockwise => !counter_clockwise,
+        };
+
+        match self.cull_mode {
+            CullMode::None => false,
+            CullMode::Back => !front_facing,
+            CullMode::Front => front_facing,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DST: Vec3 = Vec3::new(0.2, 0.4, 0.8);
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).abs().max() < 1e-6
+    }
+
+    #[test]
+    fn blend_equations() {
+        let src = Vec4::new(1.0, 0.5, 0.0, 0.25);
+        assert!(close(BlendMode::Opaque.blend(src, DST), Vec3::new(1.0, 0.5, 0.0)));
+        assert!(close(BlendMode::Alpha.blend(src, DST), Vec3::new(0.4, 0.425, 0.6)));
+        assert!(close(BlendMode::Additive.blend(src, DST), Vec3::new(0.45, 0.525, 0.8)));
+        assert!(close(BlendMode::Multiply.blend(src, DST), Vec3::new(0.2, 0.35, 0.6)));
+        assert!(close(BlendMode::Premultiplied.blend(src, DST), Vec3::new(1.15, 0.8, 0.6)));
+    }
+
+    #[test]
+    fn alpha_limits() {
+        let src = Vec4::new(1.0, 0.5, 0.0, 1.0);
+        // Opacidad total: alfa y premultiplicado reemplazan el destino
+        for mode in [BlendMode::Alpha, BlendMode::Premultiplied] {
+            assert!(close(mode.blend(src, DST), src.xyz()));
+        }
+        // Transparencia total: el destino queda igual
+        let clear = Vec4::new(1.0, 0.5, 0.0, 0.0);
+        for mode in [BlendMode::Alpha, BlendMode::Additive, BlendMode::Multiply] {
+            assert!(close(mode.blend(clear, DST), DST));
+        }
+        // La opacidad se recorta a [0, 1]
+        assert!(close(BlendMode::Alpha.blend(Vec4::new(1.0, 0.5, 0.0, 3.0), DST), src.xyz()));
+    }
+
+    #[test]
+    fn blended_states_skip_depth_writes() {
+        assert_eq!(RenderState::blended(BlendMode::Opaque), RenderState::opaque());
+        assert!(!RenderState::blended(BlendMode::Alpha).depth_write);
+        assert_eq!(BlendMode::from_name("Additive"), Some(BlendMode::Additive));
+        assert_eq!(BlendMode::from_name("screen"), None);
+    }
+}
@@ -0,0 +1,310 @@
+use crate::framebuffer::{Framebuffer, Tile, TILE_SIZE};
+use crate::triangle::Triangle;
+use crate::vertex::Vertex;
+use crate::fragment::Fragment;
+use crate::color::Color;
+use crate::shaders::{ShaderProgram, Uniforms};
+use crate::clipping::{clip_line, clip_triangle, is_inside, to_screen};
+use crate::pipeline::{PipelineState, RenderMode, RenderState, TransparencyMode};
+use crate::scene::Scene;
+use crate::cubemap::CubeMap;
+
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+// Dibuja todos los nodos de la escena sobre el mismo z-buffer y resuelve el
+// framebuffer al final. Los nodos sin shader propio usan `default_shader`.
+// Primero van los opacos; después los transparentes, del más lejano al más
+// cercano, para que cada uno se mezcle sobre lo que tiene detrás. Con
+// `TransparencyMode::OrderIndependent` el orden no importa: el A-buffer los
+// ordena por píxel en `resolve`.
+pub fn render_scene(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    scene: &Scene,
+    default_shader: &ShaderProgram,
+    pipeline: &PipelineState,
+) {
+    let order_independent = pipeline.transparency == TransparencyMode::OrderIndependent;
+    framebuffer.set_order_independent(order_independent);
+
+    let (mut transparent, opaque): (Vec<_>, Vec<_>) = scene
+        .draw_list()
+        .into_iter()
+        .map(|item| {
+            let shader = item.shader.unwrap_or(default_shader);
+            (item, shader)
+        })
+        .partition(|(_, shader)| shader.is_transparent());
+
+    for (item, shader) in opaque {
+        let node_uniforms = Uniforms { model_matrix: item.model_matrix, ..uniforms.clone() };
+        render(framebuffer, &node_uniforms, &item.model.vertices, &item.model.indices, shader, pipeline);
+    }
+
+    // Los modelos se normalizan y centran en su origen, así que la traslación
+    // de la matriz de mundo es una buena referencia de su posición
+    let view_depth = |model_matrix: &Mat4| -(uniforms.view_matrix * model_matrix.column(3)).z;
+    if !order_independent {
+        transparent.sort_by(|(a, _), (b, _)| view_depth(&b.model_matrix).total_cmp(&view_depth(&a.model_matrix)));
+    }
+
+    for (item, shader) in transparent {
+        let node_uniforms = Uniforms { model_matrix: item.model_matrix, ..uniforms.clone() };
+        render(framebuffer, &node_uniforms, &item.model.vertices, &item.model.indices, shader, pipeline);
+    }
+
+    framebuffer.resolve();
+}
+
+// Pinta el cubemap como fondo en todas las muestras. Solo usa la rotación de
+// la cámara, así el cielo parece infinitamente lejano y no se mueve al hacer
+// zoom. Va justo después de `clear()` y antes de dibujar la escena.
+pub fn render_skybox(framebuffer: &mut Framebuffer, uniforms: &Uniforms, environment: &CubeMap) {
+    let mut rotation = uniforms.view_matrix;
+    rotation[(0, 3)] = 0.0;
+    rotation[(1, 3)] = 0.0;
+    rotation[(2, 3)] = 0.0;
+    let inverse = (uniforms.projection_matrix * rotation)
+        .try_inverse()
+        .unwrap_or_else(Mat4::identity);
+
+    let (width, height, samples) = (framebuffer.width, framebuffer.height, framebuffer.samples);
+    framebuffer
+        .sample_buffer
+        .par_chunks_mut(width * samples)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let ndc_y = 1.0 - (y as f32 + 0.5) / height as f32 * 2.0;
+            for (x, pixel) in row.chunks_mut(samples).enumerate() {
+                let ndc_x = (x as f32 + 0.5) / width as f32 * 2.0 - 1.0;
+                // Punto del plano lejano bajo el centro del píxel, en espacio de mundo
+                let far = inverse * Vec4::new(ndc_x, ndc_y, 1.0, 1.0);
+                let color = environment.sample(&(far.xyz() / far.w));
+                pixel.fill(color.to_linear());
+            }
+        });
+}
+
+// Dibuja un modelo indexado. No resuelve el framebuffer para poder acumular
+// varios modelos; quien llama debe ejecutar `framebuffer.resolve()` al terminar.
+pub fn render(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    vertex_array: &[Vertex],
+    indices: &[u32],
+    shader: &ShaderProgram,
+    pipeline: &PipelineState,
+) {
+    // Cache post-transformación: cada vértice único pasa una sola vez por el
+    // vertex shader y los triángulos lo reutilizan a través del index buffer
+    let transformed_vertices: Vec<Vertex> = vertex_array
+        .par_iter()
+        .map(|vertex| shader.vertex.shade(vertex, uniforms))
+        .collect();
+
+    // Triángulos de pantalla de cada triángulo del index buffer; queda vacío
+    // si el triángulo cae fuera del frustum o se descarta por culling
+    let clipped: Vec<Vec<Triangle>> = indices
+        .par_chunks_exact(3)
+        .map(|triangle| {
+            let mut polygon = clip_triangle(
+                &transformed_vertices[triangle[0] as usize],
+                &transformed_vertices[triangle[1] as usize],
+                &transformed_vertices[triangle[2] as usize],
+            );
+
+            for vertex in &mut polygon {
+                to_screen(vertex, &uniforms.viewport_matrix);
+            }
+
+            // Triangulación en abanico del polígono recortado
+            (1..polygon.len().saturating_sub(1))
+                .map(|j| {
+                    Triangle::new_from_vertices(
+                        polygon[0].clone(),
+                        polygon[j].clone(),
+                        polygon[j + 1].clone(),
+                    )
+                })
+                .filter(|triangle| !triangle.is_culled(pipeline))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    // Los modos de líneas y puntos usan la topología original del modelo: las
+    // aristas del recorte y las diagonales del abanico no son aristas de la malla
+    let visible: Vec<&[u32]> = indices
+        .chunks_exact(3)
+        .zip(&clipped)
+        .filter(|(_, triangles)| !triangles.is_empty())
+        .map(|(triangle, _)| triangle)
+        .collect();
+    let mut triangles: Vec<Triangle> = clipped.into_iter().flatten().collect();
+
+    // Los transparentes se mezclan en orden de atrás hacia adelante. El orden
+    // es por centroide, así que los triángulos que se cruzan pueden fallar;
+    // con el A-buffer no hace falta ordenar.
+    if shader.is_transparent() && pipeline.transparency == TransparencyMode::Sorted {
+        triangles.sort_by(|a, b| b.view_depth().total_cmp(&a.view_depth()));
+    }
+
+    let shade = |fragment: &Fragment| shader.fragment.shade_hdr(fragment, uniforms);
+    let state = shader.render_state;
+    let antialiased = pipeline.line_antialiasing;
+
+    match pipeline.render_mode {
+        RenderMode::Solid => {
+            rasterize_triangles(framebuffer, &triangles, &state, shade);
+        }
+        RenderMode::Wireframe => {
+            let segments = edge_segments(&transformed_vertices, &visible, &uniforms.viewport_matrix);
+            draw_edges(framebuffer, &segments, WIREFRAME_COLOR, false, antialiased, &state);
+        }
+        RenderMode::Points => {
+            let points = vertex_points(&transformed_vertices, &visible, &uniforms.viewport_matrix);
+            draw_points(framebuffer, &points, WIREFRAME_COLOR, &state);
+        }
+        RenderMode::SolidWireframe => {
+            rasterize_triangles(framebuffer, &triangles, &state, shade);
+            let segments = edge_segments(&transformed_vertices, &visible, &uniforms.viewport_matrix);
+            draw_edges(framebuffer, &segments, OVERLAY_COLOR, true, antialiased, &state);
+        }
+        RenderMode::HiddenLine => {
+            // Las caras solo llenan el z-buffer, sin tocar el color, y ocultan las aristas de atrás
+            let depth_only = RenderState { color_write: false, ..RenderState::opaque() };
+            rasterize_triangles(framebuffer, &triangles, &depth_only, |_| Vec4::zeros());
+            let segments = edge_segments(&transformed_vertices, &visible, &uniforms.viewport_matrix);
+            draw_edges(framebuffer, &segments, WIREFRAME_COLOR, true, antialiased, &state);
+        }
+    }
+}
+
+const WIREFRAME_COLOR: Color = Color { r: 230, g: 230, b: 230, a: 255 };
+const OVERLAY_COLOR: Color = Color { r: 10, g: 10, b: 10, a: 255 };
+const POINT_SIZE: i32 = 3;
+
+fn rasterize_triangles<S>(framebuffer: &mut Framebuffer, triangles: &[Triangle], state: &RenderState, shade: S)
+where
+    S: Fn(&Fragment) -> Vec4 + Sync,
+{
+    let mut tiles = framebuffer.tiles_mut(TILE_SIZE);
+    let bins = bin_triangles(triangles, &tiles);
+
+    tiles
+        .par_iter_mut()
+        .zip(bins.par_iter())
+        .for_each(|(tile, bin)| {
+            rasterize_tile(tile, triangles, bin, state, &shade);
+        });
+}
+
+// Aristas únicas de los triángulos visibles como pares de índices (menor, mayor),
+// en orden de aparición para que el resultado no dependa del hash
+fn mesh_edges(triangles: &[&[u32]]) -> Vec<(u32, u32)> {
+    let mut seen = HashSet::new();
+    let mut edges = Vec::new();
+    for triangle in triangles {
+        for (a, b) in [(triangle[0], triangle[1]), (triangle[1], triangle[2]), (triangle[2], triangle[0])] {
+            let edge = (a.min(b), a.max(b));
+            if seen.insert(edge) {
+                edges.push(edge);
+            }
+        }
+    }
+    edges
+}
+
+// Segmentos de pantalla de las aristas únicas, recortados contra el frustum
+fn edge_segments(vertices: &[Vertex], triangles: &[&[u32]], viewport_matrix: &Mat4) -> Vec<(Vec3, Vec3)> {
+    mesh_edges(triangles)
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let (mut from, mut to) = clip_line(&vertices[a as usize], &vertices[b as usize])?;
+            to_screen(&mut from, viewport_matrix);
+            to_screen(&mut to, viewport_matrix);
+            Some((from.transformed_position, to.transformed_position))
+        })
+        .collect()
+}
+
+// Posición de pantalla de cada vértice único de los triángulos visibles que
+// quede dentro del frustum
+fn vertex_points(vertices: &[Vertex], triangles: &[&[u32]], viewport_matrix: &Mat4) -> Vec<Vec3> {
+    let mut seen = HashSet::new();
+    let mut points = Vec::new();
+    for &index in triangles.iter().flat_map(|triangle| triangle.iter()) {
+        let vertex = &vertices[index as usize];
+        if seen.insert(index) && is_inside(vertex) {
+            let mut vertex = vertex.clone();
+            to_screen(&mut vertex, viewport_matrix);
+            points.push(vertex.transformed_position);
+        }
+    }
+    points
+}
+
+// Las líneas y los puntos pasan por `Framebuffer::point`, que aplica el
+// stencil y `color_write` del programa igual que el rasterizador de triángulos
+fn draw_edges(
+    framebuffer: &mut Framebuffer,
+    segments: &[(Vec3, Vec3)],
+    color: Color,
+    depth_test: bool,
+    antialiased: bool,
+    state: &RenderState,
+) {
+    for &(from, to) in segments {
+        framebuffer.line(from, to, color, depth_test, antialiased, state);
+    }
+}
+
+fn draw_points(framebuffer: &mut Framebuffer, points: &[Vec3], color: Color, state: &RenderState) {
+    for &point in points {
+        framebuffer.dot(point, POINT_SIZE, color, false, state);
+    }
+}
+
+// Asigna cada triángulo a los tiles que toca su bounding box. Los índices
+// conservan el orden de envío, así el resultado no depende del número de hilos.
+fn bin_triangles(triangles: &[Triangle], tiles: &[Tile]) -> Vec<Vec<usize>> {
+    let mut bins = vec![Vec::new(); tiles.len()];
+    let Some(last) = tiles.last() else {
+        return bins;
+    };
+
+    let tiles_x = last.x / TILE_SIZE + 1;
+    let tiles_y = last.y / TILE_SIZE + 1;
+    let max_x = (last.x + last.width) as i32 - 1;
+    let max_y = (last.y + last.height) as i32 - 1;
+
+    for (index, triangle) in triangles.iter().enumerate() {
+        let (min_tx, min_ty, max_tx, max_ty) = triangle.bounding_box();
+        if max_tx < 0 || max_ty < 0 || min_tx > max_x || min_ty > max_y {
+            continue;
+        }
+
+        let first_col = min_tx.max(0) as usize / TILE_SIZE;
+        let last_col = (max_tx.min(max_x) as usize / TILE_SIZE).min(tiles_x - 1);
+        let first_row = min_ty.max(0) as usize / TILE_SIZE;
+        let last_row = (max_ty.min(max_y) as usize / TILE_SIZE).min(tiles_y - 1);
+
+        for row in first_row..=last_row {
+            for col in first_col..=last_col {
+                bins[row * tiles_x + col].push(index);
+            }
+        }
+    }
+
+    bins
+}
+
+fn rasterize_tile<S>(tile: &mut Tile, triangles: &[Triangle], bin: &[usize], state: &RenderState, shade: &S)
+where
+    S: Fn(&Fragment) -> Vec4,
+{
+    for &index in bin {
+        triangles[index].rasterize(tile, state, shade);
+    }
+}
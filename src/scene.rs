@@ -0,0 +1,414 @@
+use nalgebra_glm::{Mat4, Vec3};
+use crate::obj_loader::Model;
+use crate::pipeline::BlendMode;
+use crate::shaders::{create_model_matrix, ShaderProgram};
+use crate::shader_registry::ShaderRegistry;
+use crate::stencil::StencilState;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+// Tamaño al que se normaliza cada modelo cargado desde una escena
+const MODEL_SIZE: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Vec3,
+    pub scale: f32,
+}
+
+impl Transform {
+    pub fn new(translation: Vec3, rotation: Vec3, scale: f32) -> Self {
+        Transform { translation, rotation, scale }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        create_model_matrix(self.translation, self.scale, self.rotation)
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), 1.0)
+    }
+}
+
+pub struct SceneNode {
+    pub name: String,
+    // Índice en `Scene::models`; los nodos sin modelo solo agrupan a sus hijos
+    pub model: Option<usize>,
+    pub transform: Transform,
+    // Shader propio del nodo; si es None se usa el shader activo de la escena
+    pub shader: Option<Arc<ShaderProgram>>,
+    pub children: Vec<SceneNode>,
+}
+
+impl SceneNode {
+    pub fn new(name: &str, model: Option<usize>) -> Self {
+        SceneNode {
+            name: name.to_string(),
+            model,
+            transform: Transform::default(),
+            shader: None,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn with_transform(mut self, transform: Transform) -> Self {
+        self.transform = transform;
+        self
+    }
+
+    pub fn with_shader(mut self, shader: Arc<ShaderProgram>) -> Self {
+        self.shader = Some(shader);
+        self
+    }
+
+    pub fn add_child(&mut self, child: SceneNode) {
+        self.children.push(child);
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut SceneNode> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter_mut().find_map(|child| child.find_mut(name))
+    }
+}
+
+// Un modelo listo para dibujar con su matriz de mundo ya compuesta
+pub struct DrawItem<'a> {
+    pub model: &'a Model,
+    pub model_matrix: Mat4,
+    pub shader: Option<&'a ShaderProgram>,
+}
+
+pub struct Scene {
+    pub models: Vec<Model>,
+    pub nodes: Vec<SceneNode>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Scene { models: Vec::new(), nodes: Vec::new() }
+    }
+
+    // Escena de un solo modelo en el origen
+    pub fn from_model(model: Model) -> Self {
+        let mut scene = Scene::new();
+        let index = scene.add_model(model);
+        scene.add_node(SceneNode::new("modelo", Some(index)));
+        scene
+    }
+
+    pub fn add_model(&mut self, model: Model) -> usize {
+        self.models.push(model);
+        self.models.len() - 1
+    }
+
+    pub fn add_node(&mut self, node: SceneNode) {
+        self.nodes.push(node);
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut SceneNode> {
+        self.nodes.iter_mut().find_map(|node| node.find_mut(name))
+    }
+
+    pub fn node_count(&self) -> usize {
+        fn count(node: &SceneNode) -> usize {
+            1 + node.children.iter().map(count).sum::<usize>()
+        }
+        self.nodes.iter().map(count).sum()
+    }
+
+    // Esfera que encierra todos los modelos dibujados (centro, radio), en
+    // espacio de mundo; None si la escena no tiene geometría
+    pub fn bounding_sphere(&self) -> Option<(Vec3, f32)> {
+        let mut min = Vec3::repeat(f32::INFINITY);
+        let mut max = Vec3::repeat(f32::NEG_INFINITY);
+
+        for item in self.draw_list() {
+            for vertex in &item.model.vertices {
+                let p = item.model_matrix * vertex.position.push(1.0);
+                min = min.inf(&p.xyz());
+                max = max.sup(&p.xyz());
+            }
+        }
+
+        if min.x > max.x {
+            return None;
+        }
+        let center = (min + max) * 0.5;
+        Some((center, ((max - min) * 0.5).magnitude().max(1e-3)))
+    }
+
+    // Recorre el grafo en profundidad componiendo padre * local en cada nivel
+    pub fn draw_list(&self) -> Vec<DrawItem<'_>> {
+        let mut items = Vec::new();
+        for node in &self.nodes {
+            self.collect_draws(node, Mat4::identity(), &mut items);
+        }
+        items
+    }
+
+    fn collect_draws<'a>(&'a self, node: &'a SceneNode, parent: Mat4, items: &mut Vec<DrawItem<'a>>) {
+        let world = parent * node.transform.matrix();
+
+        if let Some(index) = node.model {
+            items.push(DrawItem {
+                model: &self.models[index],
+                model_matrix: world,
+                shader: node.shader.as_deref(),
+            });
+        }
+
+        for child in &node.children {
+            self.collect_draws(child, world, items);
+        }
+    }
+
+    // Carga una escena desde un archivo de texto con un nodo por línea:
+    //
+    //   nombre  padre  modelo  tx ty tz  rx ry rz  escala  [shader [mezcla [zwrite|nozwrite]] [stencil=...]]
+    //
+    // `padre` y `modelo` aceptan "-" para nodos raíz o nodos sin geometría. El
+    // padre debe declararse antes que sus hijos. Las rutas de los modelos son
+    // relativas al archivo de escena y cada OBJ se carga una sola vez. Los
+    // nombres de shader se resuelven contra `registry` al cargar. `mezcla`
+    // reemplaza el modo de mezcla del shader en ese nodo y el último campo, la
+    // escritura de profundidad (por defecto la que corresponde a la mezcla).
+    // `stencil=` reemplaza el stencil del shader con el formato de
+    // `StencilState::parse`.
+    pub fn load_from_file(filename: &str, registry: &ShaderRegistry) -> Result<Self, String> {
+        let content = fs::read_to_string(filename)
+            .map_err(|e| format!("Error al leer archivo: {}", e))?;
+        let base_dir = Path::new(filename).parent().unwrap_or(Path::new(""));
+
+        let mut scene = Scene::new();
+        let mut loaded: HashMap<String, usize> = HashMap::new();
+
+        for (line_number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |message: &str| format!("Línea {}: {}", line_number + 1, message);
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.len() < 10 || parts.len() > 14 {
+                return Err(error("se esperaban entre 10 y 14 campos"));
+            }
+
+            let mut values = [0.0f32; 7];
+            for (value, part) in values.iter_mut().zip(&parts[3..10]) {
+                *value = part
+                    .parse()
+                    .map_err(|_| error(&format!("número inválido '{}'", part)))?;
+            }
+
+            let model = match parts[2] {
+                "-" => None,
+                path => {
+                    let index = match loaded.get(path) {
+                        Some(&index) => index,
+                        None => {
+                            let full_path = base_dir.join(path);
+                            let mut model = Model::load_from_file(&full_path.to_string_lossy())
+                                .map_err(|e| error(&format!("no se pudo cargar '{}': {}", path, e)))?;
+                            model.normalize_and_center(MODEL_SIZE);
+                            let index = scene.add_model(model);
+                            loaded.insert(path.to_string(), index);
+                            index
+                        }
+                    };
+                    Some(index)
+                }
+            };
+
+            let mut node = SceneNode::new(parts[0], model).with_transform(Transform::new(
+                Vec3::new(values[0], values[1], values[2]),
+                Vec3::new(values[3], values[4], values[5]),
+                values[6],
+            ));
+            if let Some(shader) = parts.get(10) {
+                let mut program = registry.lookup(shader).map_err(|e| error(&e))?;
+                // El stencil puede ir en cualquier posición después del shader;
+                // mezcla y zwrite conservan su orden
+                let (stencil, options): (Vec<&str>, Vec<&str>) =
+                    parts[11..].iter().partition(|part| part.starts_with("stencil="));
+                if stencil.len() > 1 || options.len() > 2 {
+                    return Err(error("después del shader solo van mezcla, zwrite|nozwrite y stencil=..."));
+                }
+
+                if !stencil.is_empty() || !options.is_empty() {
+                    let mut custom = (*program).clone();
+                    if let Some(blend) = options.first() {
+                        let blend_mode = BlendMode::from_name(blend).ok_or_else(|| {
+                            error(&format!(
+                                "modo de mezcla desconocido '{}' (opaque, alpha, additive, multiply o premultiplied)",
+                                blend
+                            ))
+                        })?;
+                        custom = custom.with_blend_mode(blend_mode);
+                    }
+                    if let Some(depth_write) = options.get(1) {
+                        custom = custom.with_depth_write(match *depth_write {
+                            "zwrite" => true,
+                            "nozwrite" => false,
+                            other => return Err(error(&format!("se esperaba zwrite o nozwrite, no '{}'", other))),
+                        });
+                    }
+                    if let Some(text) = stencil.first() {
+                        let state = StencilState::parse(&text["stencil=".len()..]).map_err(|e| error(&e))?;
+                        custom = custom.with_stencil(state);
+                    }
+                    program = Arc::new(custom);
+                }
+                node = node.with_shader(program);
+            }
+
+            if scene.find_mut(parts[0]).is_some() {
+                return Err(error(&format!("el nodo '{}' ya existe", parts[0])));
+            }
+
+            match parts[1] {
+                "-" => scene.add_node(node),
+                parent => scene
+                    .find_mut(parent)
+                    .ok_or_else(|| error(&format!("el padre '{}' no está definido", parent)))?
+                    .add_child(node),
+            }
+        }
+
+        if scene.nodes.is_empty() {
+            return Err(String::from("La escena no tiene nodos"));
+        }
+
+        Ok(scene)
+    }
+}
+
+impl Default for Scene {
+    fn default() -> Self {
+        Scene::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::RenderState;
+    use std::path::PathBuf;
+
+    const TRIANGLE: &str = "v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n";
+
+    // Directorio temporal propio del test con un OBJ mínimo junto a la escena
+    fn write_scene(name: &str, source: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("proyecto2_scene_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("tri.obj"), TRIANGLE).unwrap();
+        let path = dir.join("escena.txt");
+        fs::write(&path, source).unwrap();
+        path
+    }
+
+    fn load(name: &str, source: &str) -> Result<Scene, String> {
+        let path = write_scene(name, source);
+        let scene = Scene::load_from_file(&path.to_string_lossy(), &ShaderRegistry::default());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        scene
+    }
+
+    #[test]
+    fn parses_hierarchy_and_shares_models() {
+        let scene = load(
+            "hierarchy",
+            "# comentario\n\
+             sol - tri.obj 0 0 0 0 0 0 1\n\
+             \n\
+             tierra sol tri.obj 2 0 0 0 0 0 0.5 diffuse\n\
+             luna tierra - 1 0 0 0 0 0 1\n",
+        )
+        .unwrap();
+
+        assert_eq!(scene.models.len(), 1);
+        assert_eq!(scene.nodes.len(), 1);
+        assert_eq!(scene.node_count(), 3);
+
+        let items = scene.draw_list();
+        assert_eq!(items.len(), 2);
+        assert!(items[0].shader.is_none());
+        assert_eq!(items[1].shader.map(|shader| shader.name.as_str()), Some("diffuse"));
+        // La tierra hereda la transformación del sol
+        let origin = items[1].model_matrix * nalgebra_glm::Vec4::new(0.0, 0.0, 0.0, 1.0);
+        assert!((origin.x - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn children_compose_parent_transforms() {
+        let mut parent = SceneNode::new("padre", None)
+            .with_transform(Transform::new(Vec3::new(1.0, 0.0, 0.0), Vec3::zeros(), 2.0));
+        parent.add_child(SceneNode::new("hijo", Some(0)).with_transform(Transform::new(Vec3::new(1.0, 0.0, 0.0), Vec3::zeros(), 1.0)));
+        let mut scene = Scene::new();
+        scene.add_model(Model { vertices: Vec::new(), indices: Vec::new() });
+        scene.add_node(parent);
+
+        let items = scene.draw_list();
+        assert_eq!(items.len(), 1);
+        // El desplazamiento del hijo se escala por el padre: 1 + 2 * 1
+        let origin = items[0].model_matrix * nalgebra_glm::Vec4::new(0.0, 0.0, 0.0, 1.0);
+        assert!((origin.x - 3.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        let error = load("fields", "a - - 0 0 0 0 0 0\n").err().unwrap();
+        assert!(error.starts_with("Línea 1:"), "{}", error);
+
+        let error = load("number", "a - - 0 x 0 0 0 0 1\n").err().unwrap();
+        assert!(error.contains("número inválido 'x'"), "{}", error);
+
+        let error = load("parent", "a - - 0 0 0 0 0 0 1\nb c - 0 0 0 0 0 0 1\n").err().unwrap();
+        assert!(error.starts_with("Línea 2:") && error.contains("'c'"), "{}", error);
+
+        let error = load("duplicate", "a - - 0 0 0 0 0 0 1\na - - 0 0 0 0 0 0 1\n").err().unwrap();
+        assert!(error.contains("ya existe"), "{}", error);
+
+        let error = load("model", "a - falta.obj 0 0 0 0 0 0 1\n").err().unwrap();
+        assert!(error.contains("falta.obj"), "{}", error);
+
+        let error = load("shader", "a - - 0 0 0 0 0 0 1 inexistente\n").err().unwrap();
+        assert!(error.contains("Shader desconocido: inexistente"), "{}", error);
+    }
+
+    #[test]
+    fn nodes_override_blend_mode_and_depth_write() {
+        let scene = load(
+            "blend",
+            "vidrio - tri.obj 0 0 0 0 0 0 1 diffuse alpha\n\
+             brillo - tri.obj 0 0 0 0 0 0 1 diffuse additive zwrite\n\
+             casco - tri.obj 0 0 0 0 0 0 1 diffuse opaque nozwrite\n",
+        )
+        .unwrap();
+
+        let states: Vec<RenderState> = scene.draw_list().iter().map(|item| item.shader.unwrap().render_state).collect();
+        assert_eq!((states[0].blend_mode, states[0].depth_write), (BlendMode::Alpha, false));
+        assert_eq!((states[1].blend_mode, states[1].depth_write), (BlendMode::Additive, true));
+        assert_eq!((states[2].blend_mode, states[2].depth_write), (BlendMode::Opaque, false));
+        // El shader registrado no cambia
+        assert_eq!(ShaderRegistry::default().lookup("diffuse").unwrap().render_state, RenderState::opaque());
+
+        let error = load("blend_name", "a - - 0 0 0 0 0 0 1 diffuse screen\n").err().unwrap();
+        assert!(error.contains("modo de mezcla desconocido 'screen'"), "{}", error);
+        let error = load("zwrite", "a - - 0 0 0 0 0 0 1 diffuse alpha depth\n").err().unwrap();
+        assert!(error.contains("zwrite o nozwrite"), "{}", error);
+    }
+
+    #[test]
+    fn empty_scene_is_an_error() {
+        assert!(load("empty", "# nada\n").is_err());
+        assert!(Scene::load_from_file("no/existe.txt", &ShaderRegistry::default()).is_err());
+    }
+}
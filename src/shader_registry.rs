@@ -0,0 +1,141 @@
+use crate::color::Color;
+use crate::cubemap::CubeMap;
+use crate::material::Material;
+use crate::pbr::{PbrMaterial, PbrShader};
+use crate::pipeline::BlendMode;
+use crate::shaders::{
+    CelShadingShader, DiffuseShader, GlowShader, NormalMapShader, NormalMappedShader, OutlineVertexShader,
+    PhongShader, ProceduralShader, ReflectionShader, RefractionShader, ShaderProgram, StaticColorShader,
+    TextureShader,
+};
+use crate::stencil::{CompareFunction, StencilState};
+use std::sync::Arc;
+
+// Teclas numéricas disponibles para seleccionar shaders (1-9 y 0 para el décimo)
+pub const MAX_SHADER_KEYS: usize = 10;
+
+// Shaders integrados que dependen de texturas o del entorno. Se construyen una
+// sola vez (main los arma desde la configuración) y `with_builtin` los registra
+// en su lugar; `Default` usa las texturas y el cielo generados.
+pub struct BuiltinResources {
+    pub pbr: PbrShader,
+    pub texture: TextureShader,
+    pub normal_mapped: NormalMappedShader,
+    pub environment: Arc<CubeMap>,
+}
+
+impl Default for BuiltinResources {
+    fn default() -> Self {
+        BuiltinResources {
+            pbr: PbrShader::new(PbrMaterial::default()),
+            texture: TextureShader::default(),
+            normal_mapped: NormalMappedShader::default(),
+            environment: Arc::new(CubeMap::default()),
+        }
+    }
+}
+
+// Shaders disponibles por nombre. El orden de registro define la tecla
+// numérica de cada uno: el primero es la tecla 1, el segundo la 2, etc.
+pub struct ShaderRegistry {
+    shaders: Vec<Arc<ShaderProgram>>,
+}
+
+impl ShaderRegistry {
+    pub fn new() -> Self {
+        ShaderRegistry { shaders: Vec::new() }
+    }
+
+    pub fn with_builtin(resources: BuiltinResources) -> Self {
+        let mut registry = ShaderRegistry::new();
+        registry.register(ShaderProgram::new("static_color", StaticColorShader::default()).with_description("Estático"));
+        registry.register(ShaderProgram::new("diffuse", DiffuseShader::default()).with_description("Difuso"));
+        registry.register(ShaderProgram::new("cel_shading", CelShadingShader::default()).with_description("Cel Shading"));
+        registry.register(ShaderProgram::new("procedural", ProceduralShader::default()).with_description("Procedural"));
+        registry.register(ShaderProgram::new("normal_map", NormalMapShader).with_description("Normal Map"));
+        registry.register(ShaderProgram::new("blinn_phong", PhongShader::blinn(Material::default())).with_description("Blinn-Phong"));
+        registry.register(ShaderProgram::new("phong", PhongShader::classic(Material::default())).with_description("Phong"));
+        registry.register(ShaderProgram::new("pbr", resources.pbr).with_description("PBR (metallic/roughness)"));
+        registry.register(ShaderProgram::new("textured", resources.texture).with_description("Textura"));
+        registry.register(ShaderProgram::new("normal_mapped", resources.normal_mapped).with_description("Normal mapping (espacio tangente)"));
+        registry.register(ShaderProgram::new("reflective", ReflectionShader::new(resources.environment.clone())).with_description("Reflexión del entorno"));
+        registry.register(ShaderProgram::new("refractive", RefractionShader::new(resources.environment, 1.52)).with_description("Refracción (vidrio)"));
+        registry.register(
+            ShaderProgram::new("glass", PhongShader::blinn(Material::glass(Color::new(170, 210, 255), 0.35)))
+                .with_description("Vidrio transparente (mezcla alfa)")
+                .with_blend_mode(BlendMode::Alpha),
+        );
+        registry.register(
+            ShaderProgram::new("glow", GlowShader::default())
+                .with_description("Halo de energía (aditivo)")
+                .with_blend_mode(BlendMode::Additive),
+        );
+        // Máscara y contorno: un nodo con `stencil_mask` marca con 1 la silueta
+        // del modelo y uno con `outline` dibuja el modelo inflado fuera de ella
+        registry.register(
+            ShaderProgram::new("stencil_mask", StaticColorShader::default())
+                .with_description("Máscara de stencil (sin color)")
+                .with_color_write(false)
+                .with_depth_write(false)
+                .with_stencil(StencilState::write(1)),
+        );
+        registry.register(
+            ShaderProgram::new("outline", StaticColorShader { color: Color::new(255, 170, 0) })
+                .with_description("Contorno (fuera de la máscara de stencil)")
+                .with_vertex(OutlineVertexShader::default())
+                .with_stencil(StencilState::new(CompareFunction::NotEqual, 1)),
+        );
+        registry
+    }
+
+    // Registra un shader; si ya existe uno con el mismo nombre lo reemplaza
+    // conservando su tecla
+    pub fn register(&mut self, program: ShaderProgram) -> Arc<ShaderProgram> {
+        let program = Arc::new(program);
+        match self.shaders.iter_mut().find(|existing| existing.name == program.name) {
+            Some(existing) => *existing = program.clone(),
+            None => self.shaders.push(program.clone()),
+        }
+        program
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<ShaderProgram>> {
+        self.shaders.iter().find(|program| program.name == name).cloned()
+    }
+
+    // Igual que `get`, pero con un mensaje que lista los nombres válidos
+    pub fn lookup(&self, name: &str) -> Result<Arc<ShaderProgram>, String> {
+        self.get(name).ok_or_else(|| {
+            format!("Shader desconocido: {} (disponibles: {})", name, self.names().join(", "))
+        })
+    }
+
+    // Shader asociado a la tecla numérica `key` (1-10, donde 10 es la tecla 0)
+    pub fn by_key(&self, key: usize) -> Option<Arc<ShaderProgram>> {
+        if key == 0 || key > MAX_SHADER_KEYS {
+            return None;
+        }
+        self.shaders.get(key - 1).cloned()
+    }
+
+    // Shader registrado después de `name`, volviendo al primero al final; sirve
+    // para llegar a los que no tienen tecla numérica
+    pub fn next_after(&self, name: &str) -> Option<Arc<ShaderProgram>> {
+        let index = self.shaders.iter().position(|program| program.name == name)?;
+        self.shaders.get((index + 1) % self.shaders.len()).cloned()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.shaders.iter().map(|program| program.name.as_str()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<ShaderProgram>> {
+        self.shaders.iter()
+    }
+}
+
+impl Default for ShaderRegistry {
+    fn default() -> Self {
+        ShaderRegistry::with_builtin(BuiltinResources::default())
+    }
+}
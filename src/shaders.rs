@@ -2,14 +2,53 @@ use nalgebra_glm::{Vec3, Vec4, Mat4};
 use crate::vertex::Vertex;
 use crate::fragment::Fragment;
 use crate::color::Color;
+use crate::cubemap::CubeMap;
+use crate::material::Material;
+use crate::pipeline::{BlendMode, RenderState};
+use crate::light::{Light, LightSample};
+use crate::shadow::ShadowMap;
+use crate::stencil::StencilState;
+use crate::texture::Texture;
+use std::sync::Arc;
 
+#[derive(Clone)]
 pub struct Uniforms {
     pub model_matrix: Mat4,
     pub view_matrix: Mat4,
     pub projection_matrix: Mat4,
     pub viewport_matrix: Mat4,
     pub time: f32,
-    pub light_dir: Vec3,
+    pub lights: Vec<Light>,
+    // Shadow map de cada luz en el mismo orden que `lights` (None si no proyecta sombras)
+    pub shadow_maps: Vec<Option<Arc<ShadowMap>>>,
+    pub camera_position: Vec3,
+}
+
+impl Uniforms {
+    // Contribución de cada luz encendida que alcanza `point` (espacio de mundo),
+    // atenuada por su shadow map. `normal` se usa para el bias de las sombras.
+    pub fn lights_at<'a>(&'a self, point: &'a Vec3, normal: &'a Vec3) -> impl Iterator<Item = LightSample> + 'a {
+        self.lights.iter().enumerate().filter_map(move |(i, light)| {
+            let mut sample = light.illuminate(point)?;
+            if let Some(Some(shadow_map)) = self.shadow_maps.get(i) {
+                let visibility = shadow_map.visibility(point, normal, &sample.direction);
+                if visibility <= 0.0 {
+                    return None;
+                }
+                sample.radiance *= visibility;
+            }
+            Some(sample)
+        })
+    }
+
+    // Suma de radiancia * max(0, N·L) de todas las luces
+    pub fn diffuse_irradiance(&self, point: &Vec3, normal: &Vec3) -> Vec3 {
+        let mut irradiance = Vec3::zeros();
+        for light in self.lights_at(point, normal) {
+            irradiance += light.radiance * nalgebra_glm::dot(normal, &light.direction).max(0.0);
+        }
+        irradiance
+    }
 }
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
@@ -20,17 +59,8 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         1.0
     );
 
-    let transformed = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
-
-    let w = transformed.w;
-    let ndc_position = Vec4::new(
-        transformed.x / w,
-        transformed.y / w,
-        transformed.z / w,
-        1.0
-    );
-
-    let screen_position = uniforms.viewport_matrix * ndc_position;
+    let world_position = uniforms.model_matrix * position;
+    let clip_position = uniforms.projection_matrix * uniforms.view_matrix * world_position;
 
     let model_mat3 = Mat4::new(
         uniforms.model_matrix[(0, 0)], uniforms.model_matrix[(0, 1)], uniforms.model_matrix[(0, 2)], 0.0,
@@ -43,79 +73,540 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
     let transformed_normal = model_mat3 * normal4;
     let final_normal = Vec3::new(transformed_normal.x, transformed_normal.y, transformed_normal.z).normalize();
 
+    // La tangente rota igual que la normal y conserva el signo de la bitangente
+    let tangent4 = Vec4::new(vertex.tangent.x, vertex.tangent.y, vertex.tangent.z, 0.0);
+    let transformed_tangent = model_mat3 * tangent4;
+    let final_tangent = Vec3::new(transformed_tangent.x, transformed_tangent.y, transformed_tangent.z).normalize();
+
+    // La división por w y el viewport se aplican después del recorte (ver clipping.rs)
     let mut new_vertex = vertex.clone();
-    new_vertex.transformed_position = Vec3::new(screen_position.x, screen_position.y, screen_position.z);
+    new_vertex.clip_position = clip_position;
     new_vertex.transformed_normal = final_normal;
+    new_vertex.transformed_tangent = Vec4::new(final_tangent.x, final_tangent.y, final_tangent.z, vertex.tangent.w);
+    new_vertex.world_position = Vec3::new(world_position.x, world_position.y, world_position.z);
 
     new_vertex
 }
 
-pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: &str) -> Color {
-    match shader_type {
-        "static_color" => static_color_shader(fragment),
-        "diffuse" => diffuse_shader(fragment, uniforms),
-        "cel_shading" => cel_shading_shader(fragment, uniforms),
-        "procedural" => procedural_shader(fragment, uniforms),
-        "normal_map" => normal_map_shader(fragment),
-        _ => fragment.color
+// Etapa de vértices programable. Los datos propios del shader (colores,
+// parámetros) viven en el struct que implementa el trait; `Uniforms` solo
+// lleva el estado común del frame.
+pub trait VertexShader: Send + Sync {
+    fn shade(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex;
+}
+
+// Etapa de fragmentos programable; se ejecuta en paralelo desde varios tiles
+pub trait FragmentShader: Send + Sync {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color;
+
+    // Radiancia lineal sin límite superior que el rasterizador escribe en el
+    // framebuffer HDR, con la opacidad en `w`. Por defecto decodifica el color
+    // sRGB de `shade`; los shaders que suman varias luces la implementan para
+    // no saturar antes del tone mapping.
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        self.shade(fragment, uniforms).to_linear_rgba()
     }
 }
 
-fn static_color_shader(_fragment: &Fragment) -> Color {
-    Color::new(255, 255, 0)
+// Permite usar closures como fragment shaders rápidos
+impl<F> FragmentShader for F
+where
+    F: Fn(&Fragment, &Uniforms) -> Color + Send + Sync,
+{
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        self(fragment, uniforms)
+    }
 }
 
-fn diffuse_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let light_dir = uniforms.light_dir.normalize();
-    let normal = fragment.normal.normalize();
-    
-    let diffuse = nalgebra_glm::dot(&normal, &light_dir).max(0.0);
-    let ambient = 0.3;
-    
-    let intensity = ambient + diffuse * 0.7;
-    
-    let base_color = Color::new(255, 200, 100);
-    base_color * intensity
+// Vertex shader por defecto: transforma a clip space y rota la normal
+pub struct StandardVertexShader;
+
+impl VertexShader for StandardVertexShader {
+    fn shade(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        vertex_shader(vertex, uniforms)
+    }
 }
 
-fn cel_shading_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let light_dir = uniforms.light_dir.normalize();
-    let normal = fragment.normal.normalize();
-    
-    let diffuse = nalgebra_glm::dot(&normal, &light_dir).max(0.0);
-    
-    let intensity = if diffuse > 0.8 {
-        1.0
-    } else if diffuse > 0.5 {
-        0.6
-    } else if diffuse > 0.2 {
-        0.4
-    } else {
-        0.2
-    };
-    
-    let base_color = Color::new(100, 150, 255);
-    base_color * intensity
-}
-
-fn procedural_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let x = fragment.position.x;
-    let y = fragment.position.y;
-    let t = uniforms.time;
-    
-    let pattern = ((x * 0.1 + t).sin() * (y * 0.1 + t).cos() * 0.5 + 0.5).abs();
-    
-    Color::from_float(pattern, 1.0 - pattern, 0.5)
-}
-
-fn normal_map_shader(fragment: &Fragment) -> Color {
-    let normal = fragment.normal.normalize();
-    
-    Color::from_float(
-        (normal.x + 1.0) * 0.5,
-        (normal.y + 1.0) * 0.5,
-        (normal.z + 1.0) * 0.5,
-    )
+// Infla el modelo a lo largo de sus normales antes de transformarlo. Dibujado
+// con un stencil test que descarta la silueta original produce un contorno.
+pub struct OutlineVertexShader {
+    // Grosor en unidades del modelo
+    pub width: f32,
+}
+
+impl Default for OutlineVertexShader {
+    fn default() -> Self {
+        OutlineVertexShader { width: 0.03 }
+    }
+}
+
+impl VertexShader for OutlineVertexShader {
+    fn shade(&self, vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
+        let mut inflated = vertex.clone();
+        inflated.position += vertex.normal.normalize() * self.width;
+        vertex_shader(&inflated, uniforms)
+    }
+}
+
+// Par vertex + fragment shader que el renderer ejecuta para un modelo, con la
+// forma en que su resultado se mezcla en el framebuffer. Hace las veces de
+// material: los nodos de la escena lo referencian por nombre. Los shaders se
+// comparten entre copias, así una copia puede cambiar solo el `render_state`.
+#[derive(Clone)]
+pub struct ShaderProgram {
+    pub name: String,
+    pub description: String,
+    pub vertex: Arc<dyn VertexShader>,
+    pub fragment: Arc<dyn FragmentShader>,
+    pub render_state: RenderState,
+}
+
+impl ShaderProgram {
+    pub fn new<F: FragmentShader + 'static>(name: &str, fragment: F) -> Self {
+        ShaderProgram {
+            name: name.to_string(),
+            description: name.to_string(),
+            vertex: Arc::new(StandardVertexShader),
+            fragment: Arc::new(fragment),
+            render_state: RenderState::opaque(),
+        }
+    }
+
+    pub fn with_description(mut self, description: &str) -> Self {
+        self.description = description.to_string();
+        self
+    }
+
+    pub fn with_vertex<V: VertexShader + 'static>(mut self, vertex: V) -> Self {
+        self.vertex = Arc::new(vertex);
+        self
+    }
+
+    // Los modos transparentes desactivan la escritura de profundidad; usar
+    // `with_depth_write` después para cambiarlo. El stencil y `color_write` se conservan.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.render_state = RenderState {
+            color_write: self.render_state.color_write,
+            stencil: self.render_state.stencil,
+            ..RenderState::blended(blend_mode)
+        };
+        self
+    }
+
+    pub fn with_depth_write(mut self, depth_write: bool) -> Self {
+        self.render_state.depth_write = depth_write;
+        self
+    }
+
+    // Sin color el programa solo escribe profundidad y stencil (máscaras)
+    pub fn with_color_write(mut self, color_write: bool) -> Self {
+        self.render_state.color_write = color_write;
+        self
+    }
+
+    pub fn with_stencil(mut self, stencil: StencilState) -> Self {
+        self.render_state.stencil = stencil;
+        self
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.render_state.is_transparent()
+    }
+}
+
+pub struct StaticColorShader {
+    pub color: Color,
+}
+
+impl Default for StaticColorShader {
+    fn default() -> Self {
+        StaticColorShader { color: Color::new(255, 255, 0) }
+    }
+}
+
+impl FragmentShader for StaticColorShader {
+    fn shade(&self, _fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+        self.color
+    }
+}
+
+pub struct DiffuseShader {
+    pub base_color: Color,
+    pub ambient: f32,
+    pub diffuse: f32,
+}
+
+impl Default for DiffuseShader {
+    fn default() -> Self {
+        DiffuseShader { base_color: Color::new(255, 200, 100), ambient: 0.3, diffuse: 0.7 }
+    }
+}
+
+impl FragmentShader for DiffuseShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        Color::from_linear_rgba(self.shade_hdr(fragment, uniforms))
+    }
+
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        let normal = fragment.normal.normalize();
+
+        let irradiance = uniforms.diffuse_irradiance(&fragment.world_position, &normal);
+
+        let intensity = Vec3::repeat(self.ambient) + irradiance * self.diffuse;
+
+        self.base_color.to_linear().component_mul(&intensity).push(self.base_color.alpha())
+    }
+}
+
+// Color difuso tomado de una textura con las UV interpoladas del fragmento
+pub struct TextureShader {
+    pub texture: Arc<Texture>,
+    pub ambient: f32,
+    pub diffuse: f32,
+}
+
+impl TextureShader {
+    pub fn new(texture: Arc<Texture>) -> Self {
+        TextureShader { texture, ambient: 0.3, diffuse: 0.7 }
+    }
+}
+
+impl Default for TextureShader {
+    fn default() -> Self {
+        TextureShader::new(Arc::new(Texture::default()))
+    }
+}
+
+impl FragmentShader for TextureShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        Color::from_linear_rgba(self.shade_hdr(fragment, uniforms))
+    }
+
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        let normal = fragment.normal.normalize();
+        let texel = self.texture.sample_fragment(fragment);
+
+        let irradiance = uniforms.diffuse_irradiance(&fragment.world_position, &normal);
+        let intensity = Vec3::repeat(self.ambient) + irradiance * self.diffuse;
+
+        texel.to_linear().component_mul(&intensity).push(texel.alpha())
+    }
+}
+
+pub struct CelShadingShader {
+    pub base_color: Color,
+}
+
+impl Default for CelShadingShader {
+    fn default() -> Self {
+        CelShadingShader { base_color: Color::new(100, 150, 255) }
+    }
+}
+
+impl FragmentShader for CelShadingShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        Color::from_linear_rgba(self.shade_hdr(fragment, uniforms))
+    }
+
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        let normal = fragment.normal.normalize();
+
+        // Cada canal se cuantiza por separado para que las luces de color también formen bandas
+        let irradiance = uniforms.diffuse_irradiance(&fragment.world_position, &normal);
+        let band = |diffuse: f32| {
+            if diffuse > 0.8 {
+                1.0
+            } else if diffuse > 0.5 {
+                0.6
+            } else if diffuse > 0.2 {
+                0.4
+            } else {
+                0.2
+            }
+        };
+
+        self.base_color.to_linear().component_mul(&irradiance.map(band)).push(self.base_color.alpha())
+    }
+}
+
+pub struct ProceduralShader {
+    // Frecuencia del patrón en ciclos por píxel de pantalla
+    pub frequency: f32,
+}
+
+impl Default for ProceduralShader {
+    fn default() -> Self {
+        ProceduralShader { frequency: 0.1 }
+    }
+}
+
+impl FragmentShader for ProceduralShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        let x = fragment.position.x;
+        let y = fragment.position.y;
+        let t = uniforms.time;
+
+        let pattern = ((x * self.frequency + t).sin() * (y * self.frequency + t).cos() * 0.5 + 0.5).abs();
+
+        Color::from_float(pattern, 1.0 - pattern, 0.5)
+    }
+}
+
+pub struct NormalMapShader;
+
+impl FragmentShader for NormalMapShader {
+    fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms) -> Color {
+        let normal = fragment.normal.normalize();
+
+        Color::from_float(
+            (normal.x + 1.0) * 0.5,
+            (normal.y + 1.0) * 0.5,
+            (normal.z + 1.0) * 0.5,
+        )
+    }
+}
+
+// Forma de calcular el brillo especular en `PhongShader`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpecularModel {
+    // Reflejo de la luz comparado con la dirección de vista
+    Phong,
+    // Vector medio entre luz y vista comparado con la normal; más barato y
+    // con brillos más anchos para el mismo exponente
+    BlinnPhong,
+}
+
+pub struct PhongShader {
+    pub material: Material,
+    pub model: SpecularModel,
+}
+
+impl PhongShader {
+    pub fn classic(material: Material) -> Self {
+        PhongShader { material, model: SpecularModel::Phong }
+    }
+
+    pub fn blinn(material: Material) -> Self {
+        PhongShader { material, model: SpecularModel::BlinnPhong }
+    }
+}
+
+impl FragmentShader for PhongShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        Color::from_linear_rgba(self.shade_hdr(fragment, uniforms))
+    }
+
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        let color = phong_lighting(&self.material, self.model, fragment.normal.normalize(), fragment, uniforms);
+        color.push(self.material.opacity())
+    }
+}
+
+// Iluminación Phong/Blinn-Phong de todas las luces con una normal dada, para
+// que los shaders que modifican la normal (normal mapping) la reutilicen. Los
+// colores del material se pasan a lineal y la suma se devuelve sin recortar.
+pub fn phong_lighting(material: &Material, model: SpecularModel, normal: Vec3, fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    let view_dir = fragment.view_dir(&uniforms.camera_position);
+    let (diffuse, specular_color) = (material.diffuse.to_linear(), material.specular.to_linear());
+
+    let mut color = material.ambient.to_linear();
+
+    for light in uniforms.lights_at(&fragment.world_position, &normal) {
+        let light_dir = light.direction;
+        let n_dot_l = nalgebra_glm::dot(&normal, &light_dir).max(0.0);
+
+        // Sin luz directa no hay brillo, aunque la vista coincida con el reflejo
+        if n_dot_l <= 0.0 {
+            continue;
+        }
+
+        let alignment = match model {
+            SpecularModel::Phong => {
+                let reflected = normal * (2.0 * n_dot_l) - light_dir;
+                nalgebra_glm::dot(&reflected, &view_dir)
+            }
+            SpecularModel::BlinnPhong => {
+                let half_dir = (light_dir + view_dir).normalize();
+                nalgebra_glm::dot(&normal, &half_dir)
+            }
+        };
+        let specular = alignment.max(0.0).powf(material.shininess);
+
+        color += diffuse.component_mul(&light.radiance) * n_dot_l
+            + specular_color.component_mul(&light.radiance) * specular;
+    }
+
+    color
+}
+
+// Normal mapping en espacio tangente: la normal de cada fragmento se reemplaza
+// por la del normal map (convención OpenGL, verde hacia +v, como exporta
+// Blender) expresada en la base tangente/bitangente/normal interpolada
+pub struct NormalMappedShader {
+    pub normal_map: Arc<Texture>,
+    pub material: Material,
+    // Escala del relieve: 0 deja la normal original, 1 usa el mapa tal cual
+    pub strength: f32,
+}
+
+impl NormalMappedShader {
+    pub fn new(normal_map: Arc<Texture>) -> Self {
+        NormalMappedShader { normal_map, material: Material::default(), strength: 1.0 }
+    }
+}
+
+impl Default for NormalMappedShader {
+    fn default() -> Self {
+        NormalMappedShader::new(Arc::new(Texture::panel_normal_map(256, 4)))
+    }
+}
+
+impl FragmentShader for NormalMappedShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        Color::from_linear_rgba(self.shade_hdr(fragment, uniforms))
+    }
+
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        let normal = fragment.normal.normalize();
+        let tangent = fragment.tangent.xyz();
+        let tangent = (tangent - normal * nalgebra_glm::dot(&normal, &tangent)).normalize();
+        let bitangent = nalgebra_glm::cross(&normal, &tangent) * fragment.tangent.w.signum();
+
+        let texel = self.normal_map.sample_fragment(fragment);
+        let decode = |c: u8| c as f32 / 255.0 * 2.0 - 1.0;
+        let (x, y, z) = (decode(texel.r) * self.strength, decode(texel.g) * self.strength, decode(texel.b));
+
+        let mut mapped = tangent * x + bitangent * y + normal * z;
+        if !mapped.iter().all(|c| c.is_finite()) || mapped.magnitude() < 1e-6 {
+            mapped = normal;
+        }
+
+        phong_lighting(&self.material, SpecularModel::BlinnPhong, mapped.normalize(), fragment, uniforms)
+            .push(self.material.opacity())
+    }
+}
+
+// Superficie reflectante (cromo) que refleja el cubemap del entorno. La
+// reflectividad aumenta en los ángulos rasantes (Fresnel de Schlick) y el resto
+// es iluminación difusa del color base.
+pub struct ReflectionShader {
+    pub environment: Arc<CubeMap>,
+    pub base_color: Color,
+    // Fracción reflejada al mirar la superficie de frente
+    pub reflectivity: f32,
+}
+
+impl ReflectionShader {
+    pub fn new(environment: Arc<CubeMap>) -> Self {
+        ReflectionShader { environment, base_color: Color::new(180, 180, 190), reflectivity: 0.6 }
+    }
+}
+
+impl Default for ReflectionShader {
+    fn default() -> Self {
+        ReflectionShader::new(Arc::new(CubeMap::default()))
+    }
+}
+
+impl FragmentShader for ReflectionShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        Color::from_linear_rgba(self.shade_hdr(fragment, uniforms))
+    }
+
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        let normal = fragment.normal.normalize();
+        let view_dir = fragment.view_dir(&uniforms.camera_position);
+
+        let reflected = nalgebra_glm::reflect_vec(&-view_dir, &normal);
+        let environment = self.environment.sample(&reflected).to_linear();
+
+        let irradiance = uniforms.diffuse_irradiance(&fragment.world_position, &normal);
+        let diffuse = self.base_color.to_linear().component_mul(&(Vec3::repeat(0.3) + irradiance * 0.7));
+
+        let cos_theta = nalgebra_glm::dot(&normal, &view_dir).clamp(0.0, 1.0);
+        let fresnel = self.reflectivity + (1.0 - self.reflectivity) * (1.0 - cos_theta).powi(5);
+        nalgebra_glm::lerp(&diffuse, &environment, fresnel).push(self.base_color.alpha())
+    }
+}
+
+// Material transparente (vidrio) de una sola interfaz: el rayo de vista se
+// refracta con la ley de Snell y se mezcla con el reflejo según Fresnel
+pub struct RefractionShader {
+    pub environment: Arc<CubeMap>,
+    // Índice de refracción: 1.0 aire, 1.33 agua, 1.52 vidrio, 2.42 diamante
+    pub ior: f32,
+    // Color que absorbe la luz transmitida
+    pub tint: Color,
+}
+
+impl RefractionShader {
+    pub fn new(environment: Arc<CubeMap>, ior: f32) -> Self {
+        RefractionShader { environment, ior: ior.max(1.0), tint: Color::new(220, 240, 255) }
+    }
+}
+
+impl Default for RefractionShader {
+    fn default() -> Self {
+        RefractionShader::new(Arc::new(CubeMap::default()), 1.52)
+    }
+}
+
+impl FragmentShader for RefractionShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        Color::from_linear_rgba(self.shade_hdr(fragment, uniforms))
+    }
+
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        let normal = fragment.normal.normalize();
+        let view_dir = fragment.view_dir(&uniforms.camera_position);
+        let incident = -view_dir;
+
+        let reflected = self.environment.sample(&nalgebra_glm::reflect_vec(&incident, &normal)).to_linear();
+        let refracted_dir = nalgebra_glm::refract_vec(&incident, &normal, 1.0 / self.ior);
+        // refract devuelve cero cuando hay reflexión total interna
+        if refracted_dir.magnitude() < 1e-6 {
+            return reflected.push(1.0);
+        }
+
+        let refracted = self.environment.sample(&refracted_dir).to_linear().component_mul(&self.tint.to_linear());
+
+        let f0 = ((self.ior - 1.0) / (self.ior + 1.0)).powi(2);
+        let cos_theta = nalgebra_glm::dot(&normal, &view_dir).clamp(0.0, 1.0);
+        let fresnel = f0 + (1.0 - f0) * (1.0 - cos_theta).powi(5);
+        nalgebra_glm::lerp(&refracted, &reflected, fresnel).push(1.0)
+    }
+}
+
+// Halo de energía (escudos, toberas) que brilla en los bordes de la silueta,
+// donde la superficie se ve de canto. Pensado para mezcla aditiva: la opacidad
+// es la intensidad del borde y el centro queda invisible.
+pub struct GlowShader {
+    pub color: Color,
+    // Multiplicador de la radiancia; mayor que 1 satura hacia blanco con tone mapping
+    pub intensity: f32,
+    // Exponente del borde: valores altos lo hacen más fino
+    pub power: f32,
+}
+
+impl Default for GlowShader {
+    fn default() -> Self {
+        GlowShader { color: Color::new(80, 180, 255), intensity: 2.0, power: 2.0 }
+    }
+}
+
+impl FragmentShader for GlowShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms) -> Color {
+        Color::from_linear_rgba(self.shade_hdr(fragment, uniforms))
+    }
+
+    fn shade_hdr(&self, fragment: &Fragment, uniforms: &Uniforms) -> Vec4 {
+        let normal = fragment.normal.normalize();
+        let view_dir = fragment.view_dir(&uniforms.camera_position);
+        // Valor absoluto para que las caras traseras también brillen sin culling
+        let rim = (1.0 - nalgebra_glm::dot(&normal, &view_dir).abs()).clamp(0.0, 1.0).powf(self.power);
+
+        (self.color.to_linear() * self.intensity).push(rim)
+    }
 }
 
 pub fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
@@ -0,0 +1,299 @@
+use nalgebra_glm::{look_at, ortho, perspective, Mat4, Vec3, Vec4};
+use std::f32::consts::PI;
+use std::sync::Arc;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::framebuffer::Framebuffer;
+use crate::light::{Light, LightKind};
+use crate::pipeline::{CullMode, PipelineState};
+use crate::renderer::render;
+use crate::scene::Scene;
+use crate::shaders::{create_viewport_matrix, ShaderProgram, Uniforms};
+
+pub const DEFAULT_SHADOW_MAP_SIZE: usize = 1024;
+pub const MAX_PCF_RADIUS: usize = 4;
+
+// Parámetros comunes a todos los shadow maps del frame
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    // Resolución del shadow map (cuadrado)
+    pub size: usize,
+    // Radio del filtro PCF en texels: 0 = una muestra, 1 = 3x3, 2 = 5x5...
+    pub pcf_radius: usize,
+    // Desplazamiento hacia la luz, en texels del shadow map
+    pub bias: f32,
+    // Desplazamiento a lo largo de la normal, en texels; crece con el ángulo
+    // entre la normal y la luz, que es donde aparece el shadow acne
+    pub normal_offset: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        ShadowSettings { size: DEFAULT_SHADOW_MAP_SIZE, pcf_radius: 1, bias: 1.0, normal_offset: 1.5 }
+    }
+}
+
+// Proyección usada por el shadow map; define el tamaño de un texel en el mundo
+#[derive(Debug, Clone, Copy)]
+enum Projection {
+    // Luz direccional: el texel mide lo mismo en toda la escena
+    Orthographic { texel_size: f32 },
+    // Spot: el texel crece con la distancia a la luz
+    Perspective { position: Vec3, tan_half_fov: f32 },
+}
+
+// Profundidad de la escena vista desde una luz. Un punto está en sombra si
+// algo más cercano a la luz ocupa su texel.
+pub struct ShadowMap {
+    pub size: usize,
+    // Profundidad en NDC del punto más cercano a la luz por texel
+    pub depth: Vec<f32>,
+    pub view_projection: Mat4,
+    projection: Projection,
+    settings: ShadowSettings,
+}
+
+impl ShadowMap {
+    // Renderiza solo la profundidad de la escena desde la luz. Las luces
+    // puntuales necesitarían seis mapas (un cubemap) y no proyectan sombras.
+    pub fn render(light: &Light, scene: &Scene, settings: &ShadowSettings) -> Option<ShadowMap> {
+        if !light.enabled {
+            return None;
+        }
+        let (center, radius) = scene.bounding_sphere()?;
+        let size = settings.size.max(1);
+
+        let (view, projection_matrix, projection) = match light.kind {
+            LightKind::Directional { direction } => {
+                // Cámara ortográfica que encierra la esfera de la escena
+                let eye = center - direction * (radius * 2.0);
+                let view = look_at(&eye, &center, &up_for(&direction));
+                let projection_matrix = ortho(-radius, radius, -radius, radius, radius, radius * 3.0);
+                (view, projection_matrix, Projection::Orthographic { texel_size: 2.0 * radius / size as f32 })
+            }
+            LightKind::Spot { position, direction, outer_cone, .. } => {
+                let fov = (outer_cone * 2.0 + 0.1).min(PI - 0.1);
+                let far = (center - position).magnitude() + radius;
+                let view = look_at(&position, &(position + direction), &up_for(&direction));
+                let projection_matrix = perspective(1.0, fov, 0.05, far.max(0.1));
+                (view, projection_matrix, Projection::Perspective { position, tan_half_fov: (fov * 0.5).tan() })
+            }
+            LightKind::Point { .. } => return None,
+        };
+
+        // Solo profundidad: sin buffers de color ni fragment shader
+        let mut framebuffer = Framebuffer::new_depth_only(size, size);
+
+        let uniforms = Uniforms {
+            model_matrix: Mat4::identity(),
+            view_matrix: view,
+            projection_matrix,
+            viewport_matrix: create_viewport_matrix(size as f32, size as f32),
+            time: 0.0,
+            lights: Vec::new(),
+            shadow_maps: Vec::new(),
+            camera_position: Vec3::zeros(),
+        };
+        // Sin culling: las aletas de la nave son planos sin grosor y deben
+        // proyectar sombra por ambas caras
+        let pipeline = PipelineState { cull_mode: CullMode::None, ..PipelineState::default() };
+        let depth_only = ShaderProgram::new("shadow_depth", |_: &Fragment, _: &Uniforms| Color::black())
+            .with_color_write(false);
+
+        for item in scene.draw_list() {
+            let node_uniforms = Uniforms { model_matrix: item.model_matrix, ..uniforms.clone() };
+            render(&mut framebuffer, &node_uniforms, &item.model.vertices, &item.model.indices, &depth_only, &pipeline);
+        }
+
+        Some(ShadowMap {
+            size,
+            depth: framebuffer.zbuffer,
+            view_projection: projection_matrix * view,
+            projection,
+            settings: *settings,
+        })
+    }
+
+    // Fracción de luz que llega a `point` en [0, 1]. `light_dir` apunta hacia
+    // la luz; `normal` debe estar normalizada.
+    pub fn visibility(&self, point: &Vec3, normal: &Vec3, light_dir: &Vec3) -> f32 {
+        let texel = match self.projection {
+            Projection::Orthographic { texel_size } => texel_size,
+            Projection::Perspective { position, tan_half_fov } => {
+                2.0 * (point - position).magnitude() * tan_half_fov / self.size as f32
+            }
+        };
+
+        // Bias: se aleja el punto de la superficie hacia la luz y a lo largo de
+        // la normal, en proporción al tamaño del texel
+        let cos_theta = nalgebra_glm::dot(normal, light_dir).clamp(0.0, 1.0);
+        let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+        let offset = light_dir * (self.settings.bias * texel) + normal * (self.settings.normal_offset * texel * sin_theta);
+        let biased = point + offset;
+
+        let clip = self.view_projection * Vec4::new(biased.x, biased.y, biased.z, 1.0);
+        if clip.w <= 0.0 {
+            return 1.0;
+        }
+        let ndc = clip.xyz() / clip.w;
+        if ndc.z > 1.0 {
+            return 1.0;
+        }
+
+        // Mismo mapeo que la matriz de viewport (y hacia abajo)
+        let x = ((ndc.x + 1.0) * 0.5 * self.size as f32).floor() as i64;
+        let y = ((1.0 - ndc.y) * 0.5 * self.size as f32).floor() as i64;
+
+        // PCF: promedio de las comparaciones en una ventana de texels
+        let radius = self.settings.pcf_radius as i64;
+        let mut lit = 0;
+        let mut total = 0;
+        for dy in -radius..=radius {
+            for dx in -radius..=radius {
+                total += 1;
+                if self.depth_at(x + dx, y + dy).is_none_or(|depth| ndc.z <= depth) {
+                    lit += 1;
+                }
+            }
+        }
+        lit as f32 / total as f32
+    }
+
+    // Fuera del mapa no hay nada que haga sombra
+    fn depth_at(&self, x: i64, y: i64) -> Option<f32> {
+        if x < 0 || y < 0 || x >= self.size as i64 || y >= self.size as i64 {
+            return None;
+        }
+        Some(self.depth[y as usize * self.size + x as usize])
+    }
+}
+
+// Shadow map de cada luz, en el mismo orden que `lights`
+pub fn render_shadow_maps(lights: &[Light], scene: &Scene, settings: &ShadowSettings) -> Vec<Option<Arc<ShadowMap>>> {
+    lights
+        .iter()
+        .map(|light| ShadowMap::render(light, scene, settings).map(Arc::new))
+        .collect()
+}
+
+// Shadow maps que se conservan entre frames. Solo se vuelven a renderizar los
+// de las luces que cambiaron (se movieron, se encendieron o se apagaron) o
+// todos si cambia la configuración; la escena no cambia durante la ejecución.
+#[derive(Default)]
+pub struct ShadowCache {
+    lights: Vec<Light>,
+    settings: Option<ShadowSettings>,
+    maps: Vec<Option<Arc<ShadowMap>>>,
+}
+
+impl ShadowCache {
+    pub fn update(&mut self, lights: &[Light], scene: &Scene, settings: &ShadowSettings) -> Vec<Option<Arc<ShadowMap>>> {
+        if self.settings != Some(*settings) || self.lights.len() != lights.len() {
+            self.maps = render_shadow_maps(lights, scene, settings);
+        } else {
+            for ((map, cached), light) in self.maps.iter_mut().zip(self.lights.iter()).zip(lights) {
+                if cached != light {
+                    *map = ShadowMap::render(light, scene, settings).map(Arc::new);
+                }
+            }
+        }
+        self.lights = lights.to_vec();
+        self.settings = Some(*settings);
+        self.maps.clone()
+    }
+}
+
+// Vector "arriba" para look_at que no sea paralelo a la dirección de la luz
+fn up_for(direction: &Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 { Vec3::new(0.0, 0.0, 1.0) } else { Vec3::new(0.0, 1.0, 0.0) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::obj_loader::Model;
+    use crate::vertex::Vertex;
+    use nalgebra_glm::Vec2;
+
+    const SIZE: usize = 4;
+
+    // Mapa de 4x4 cuya proyección es la identidad: la posición ya está en NDC
+    // y la luz mira hacia +z
+    fn flat_map(depth: Vec<f32>, pcf_radius: usize, bias: f32) -> ShadowMap {
+        ShadowMap {
+            size: SIZE,
+            depth,
+            view_projection: Mat4::identity(),
+            projection: Projection::Orthographic { texel_size: 0.5 },
+            settings: ShadowSettings { size: SIZE, pcf_radius, bias, normal_offset: 0.0 },
+        }
+    }
+
+    fn visibility(map: &ShadowMap, point: Vec3) -> f32 {
+        let towards_light = Vec3::new(0.0, 0.0, -1.0);
+        map.visibility(&point, &towards_light, &towards_light)
+    }
+
+    #[test]
+    fn points_behind_an_occluder_are_in_shadow() {
+        let map = flat_map(vec![0.0; SIZE * SIZE], 0, 0.0);
+        assert_eq!(visibility(&map, Vec3::new(0.1, 0.1, 0.5)), 0.0);
+        assert_eq!(visibility(&map, Vec3::new(0.1, 0.1, -0.5)), 1.0);
+
+        let empty = flat_map(vec![f32::INFINITY; SIZE * SIZE], 0, 0.0);
+        assert_eq!(visibility(&empty, Vec3::new(0.1, 0.1, 0.5)), 1.0);
+    }
+
+    #[test]
+    fn outside_the_map_is_lit() {
+        let map = flat_map(vec![0.0; SIZE * SIZE], 1, 0.0);
+        assert_eq!(visibility(&map, Vec3::new(3.0, 0.0, 0.5)), 1.0);
+        // Más lejos que el plano far
+        assert_eq!(visibility(&map, Vec3::new(0.0, 0.0, 1.5)), 1.0);
+    }
+
+    #[test]
+    fn bias_moves_the_point_towards_the_light() {
+        let map = flat_map(vec![0.0; SIZE * SIZE], 0, 0.0);
+        assert_eq!(visibility(&map, Vec3::new(0.1, 0.1, 0.1)), 0.0);
+        // Un texel de 0.5 de bias lleva el punto por delante del oclusor
+        let map = flat_map(vec![0.0; SIZE * SIZE], 0, 1.0);
+        assert_eq!(visibility(&map, Vec3::new(0.1, 0.1, 0.1)), 1.0);
+    }
+
+    #[test]
+    fn pcf_averages_the_neighbouring_texels() {
+        // Solo las dos columnas de la izquierda hacen sombra
+        let depth = (0..SIZE * SIZE).map(|i| if i % SIZE < 2 { 0.0 } else { f32::INFINITY }).collect::<Vec<_>>();
+        // x = 0.25 cae en la columna 2, justo a la derecha del borde
+        let point = Vec3::new(0.25, 0.1, 0.5);
+        assert_eq!(visibility(&flat_map(depth.clone(), 0, 0.0), point), 1.0);
+        assert!((visibility(&flat_map(depth.clone(), 1, 0.0), point) - 6.0 / 9.0).abs() < 1e-6);
+        // En la columna 1 la ventana de 3x3 ve una sola columna iluminada
+        assert!((visibility(&flat_map(depth, 1, 0.0), Vec3::new(-0.25, 0.1, 0.5)) - 3.0 / 9.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn directional_light_casts_the_shadow_of_a_quad() {
+        let normal = Vec3::new(0.0, 1.0, 0.0);
+        let corner = |x: f32, z: f32| Vertex::new(Vec3::new(x, 0.0, z), normal, Vec2::zeros());
+        let quad = Model {
+            vertices: vec![corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)],
+            indices: vec![0, 1, 2, 0, 2, 3],
+        };
+        let scene = Scene::from_model(quad);
+        let settings = ShadowSettings { size: 64, ..ShadowSettings::default() };
+
+        let sun = Light::directional("sol", Vec3::new(0.0, -1.0, 0.0), Color::black(), 1.0);
+        let map = ShadowMap::render(&sun, &scene, &settings).unwrap();
+        assert_eq!(map.visibility(&Vec3::new(0.2, -0.5, 0.3), &normal, &normal), 0.0);
+        assert_eq!(map.visibility(&Vec3::new(0.2, 0.5, 0.3), &normal, &normal), 1.0);
+        // La superficie del propio quad no se sombrea gracias al bias
+        assert_eq!(map.visibility(&Vec3::new(0.2, 0.0, 0.3), &normal, &normal), 1.0);
+
+        assert!(ShadowMap::render(&Light::point("foco", Vec3::zeros(), Color::black(), 1.0), &scene, &settings).is_none());
+        let mut off = sun;
+        off.enabled = false;
+        assert!(ShadowMap::render(&off, &scene, &settings).is_none());
+    }
+}
@@ -0,0 +1,355 @@
+// Comparación entre el valor de referencia y el del stencil buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareFunction {
+    Never,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+    Always,
+}
+
+impl CompareFunction {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "never" => Some(CompareFunction::Never),
+            "less" => Some(CompareFunction::Less),
+            "less-equal" => Some(CompareFunction::LessEqual),
+            "greater" => Some(CompareFunction::Greater),
+            "greater-equal" => Some(CompareFunction::GreaterEqual),
+            "equal" => Some(CompareFunction::Equal),
+            "not-equal" => Some(CompareFunction::NotEqual),
+            "always" => Some(CompareFunction::Always),
+            _ => None,
+        }
+    }
+
+    pub fn compare(self, a: u8, b: u8) -> bool {
+        match self {
+            CompareFunction::Never => false,
+            CompareFunction::Less => a < b,
+            CompareFunction::LessEqual => a <= b,
+            CompareFunction::Greater => a > b,
+            CompareFunction::GreaterEqual => a >= b,
+            CompareFunction::Equal => a == b,
+            CompareFunction::NotEqual => a != b,
+            CompareFunction::Always => true,
+        }
+    }
+}
+
+// Qué se escribe en el stencil buffer según el resultado de los tests
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StencilOp {
+    Keep,
+    Zero,
+    // Escribe el valor de referencia
+    Replace,
+    // Suma 1 sin pasar de 255
+    Increment,
+    // Suma 1 y vuelve a 0 después de 255
+    IncrementWrap,
+    // Resta 1 sin bajar de 0
+    Decrement,
+    // Resta 1 y vuelve a 255 después de 0
+    DecrementWrap,
+    Invert,
+}
+
+impl StencilOp {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "keep" => Some(StencilOp::Keep),
+            "zero" => Some(StencilOp::Zero),
+            "replace" => Some(StencilOp::Replace),
+            "increment" => Some(StencilOp::Increment),
+            "increment-wrap" => Some(StencilOp::IncrementWrap),
+            "decrement" => Some(StencilOp::Decrement),
+            "decrement-wrap" => Some(StencilOp::DecrementWrap),
+            "invert" => Some(StencilOp::Invert),
+            _ => None,
+        }
+    }
+
+    fn apply(self, value: u8, reference: u8) -> u8 {
+        match self {
+            StencilOp::Keep => value,
+            StencilOp::Zero => 0,
+            StencilOp::Replace => reference,
+            StencilOp::Increment => value.saturating_add(1),
+            StencilOp::IncrementWrap => value.wrapping_add(1),
+            StencilOp::Decrement => value.saturating_sub(1),
+            StencilOp::DecrementWrap => value.wrapping_sub(1),
+            StencilOp::Invert => !value,
+        }
+    }
+}
+
+// Stencil test de 8 bits con la semántica de OpenGL: la muestra pasa si
+// `(reference & read_mask) compare (stencil & read_mask)`. Según el resultado
+// del stencil test y del depth test se aplica `fail`, `depth_fail` o `pass`,
+// y solo cambian los bits de `write_mask`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StencilState {
+    pub enabled: bool,
+    pub compare: CompareFunction,
+    pub reference: u8,
+    pub read_mask: u8,
+    pub write_mask: u8,
+    // El stencil test falló
+    pub fail: StencilOp,
+    // Pasó el stencil test pero no el depth test
+    pub depth_fail: StencilOp,
+    // Pasaron los dos
+    pub pass: StencilOp,
+}
+
+impl StencilState {
+    pub fn disabled() -> Self {
+        StencilState {
+            enabled: false,
+            compare: CompareFunction::Always,
+            reference: 0,
+            read_mask: 0xFF,
+            write_mask: 0xFF,
+            fail: StencilOp::Keep,
+            depth_fail: StencilOp::Keep,
+            pass: StencilOp::Keep,
+        }
+    }
+
+    // Test activo que no modifica el buffer; `with_ops` define las escrituras
+    pub fn new(compare: CompareFunction, reference: u8) -> Self {
+        StencilState { enabled: true, compare, reference, ..StencilState::disabled() }
+    }
+
+    pub fn with_ops(mut self, fail: StencilOp, depth_fail: StencilOp, pass: StencilOp) -> Self {
+        self.fail = fail;
+        self.depth_fail = depth_fail;
+        self.pass = pass;
+        self
+    }
+
+    pub fn with_masks(mut self, read_mask: u8, write_mask: u8) -> Self {
+        self.read_mask = read_mask;
+        self.write_mask = write_mask;
+        self
+    }
+
+    // Estado desde texto, como en los archivos de escena:
+    //   comparación,referencia[,fail,depth_fail,pass[,read_mask,write_mask]]
+    // Sin operaciones el test no modifica el buffer.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = text.split(',').collect();
+        if ![2, 5, 7].contains(&parts.len()) {
+            return Err(format!(
+                "stencil inválido '{}' (comparación,referencia[,fail,depth_fail,pass[,read_mask,write_mask]])",
+                text
+            ));
+        }
+
+        let byte = |part: &str| part.parse::<u8>().map_err(|_| format!("valor de stencil inválido '{}' (0-255)", part));
+        let op = |part: &str| {
+            StencilOp::from_name(part).ok_or_else(|| {
+                format!(
+                    "operación de stencil desconocida '{}' (keep, zero, replace, increment, increment-wrap, decrement, decrement-wrap o invert)",
+                    part
+                )
+            })
+        };
+
+        let compare = CompareFunction::from_name(parts[0]).ok_or_else(|| {
+            format!(
+                "comparación de stencil desconocida '{}' (never, less, less-equal, greater, greater-equal, equal, not-equal o always)",
+                parts[0]
+            )
+        })?;
+        let mut state = StencilState::new(compare, byte(parts[1])?);
+        if parts.len() >= 5 {
+            state = state.with_ops(op(parts[2])?, op(parts[3])?, op(parts[4])?);
+        }
+        if parts.len() == 7 {
+            state = state.with_masks(byte(parts[5])?, byte(parts[6])?);
+        }
+        Ok(state)
+    }
+
+    // Escribe `reference` donde el fragmento es visible; sirve para marcar la
+    // silueta de un objeto o la región de un portal
+    pub fn write(reference: u8) -> Self {
+        StencilState::new(CompareFunction::Always, reference).with_ops(StencilOp::Keep, StencilOp::Keep, StencilOp::Replace)
+    }
+
+    pub fn test(&self, stencil: u8) -> bool {
+        !self.enabled || self.compare.compare(self.reference & self.read_mask, stencil & self.read_mask)
+    }
+
+    // Nuevo valor del stencil tras aplicar `op`, respetando `write_mask`
+    pub fn update(&self, op: StencilOp, stencil: u8) -> u8 {
+        if !self.enabled {
+            return stencil;
+        }
+        let value = op.apply(stencil, self.reference);
+        (stencil & !self.write_mask) | (value & self.write_mask)
+    }
+
+    // Stencil test y depth test de una muestra con la actualización del
+    // stencil que corresponda. Devuelve si la muestra es visible.
+    pub fn test_and_update(&self, stencil: &mut u8, depth_passed: bool) -> bool {
+        if !self.test(*stencil) {
+            *stencil = self.update(self.fail, *stencil);
+            return false;
+        }
+        let op = if depth_passed { self.pass } else { self.depth_fail };
+        *stencil = self.update(op, *stencil);
+        depth_passed
+    }
+}
+
+impl Default for StencilState {
+    fn default() -> Self {
+        StencilState::disabled()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Escribe un valor distinto según el resultado para saber qué operación se aplicó
+    fn tagged(compare: CompareFunction, reference: u8) -> StencilState {
+        StencilState::new(compare, reference).with_ops(StencilOp::Zero, StencilOp::Invert, StencilOp::Replace)
+    }
+
+    #[test]
+    fn disabled_state_passes_and_keeps_the_buffer() {
+        let state = StencilState::disabled();
+        let mut stencil = 7;
+        assert!(state.test_and_update(&mut stencil, true));
+        assert!(!state.test_and_update(&mut stencil, false));
+        assert_eq!(stencil, 7);
+    }
+
+    #[test]
+    fn failed_stencil_test_applies_fail() {
+        let state = tagged(CompareFunction::Equal, 3);
+        let mut stencil = 5;
+        assert!(!state.test_and_update(&mut stencil, true));
+        assert_eq!(stencil, 0);
+    }
+
+    #[test]
+    fn failed_depth_test_applies_depth_fail() {
+        let state = tagged(CompareFunction::Equal, 5);
+        let mut stencil = 5;
+        assert!(!state.test_and_update(&mut stencil, false));
+        assert_eq!(stencil, !5);
+    }
+
+    #[test]
+    fn passing_both_tests_applies_pass() {
+        let state = tagged(CompareFunction::Equal, 5).with_ops(StencilOp::Zero, StencilOp::Invert, StencilOp::Increment);
+        let mut stencil = 5;
+        assert!(state.test_and_update(&mut stencil, true));
+        assert_eq!(stencil, 6);
+    }
+
+    #[test]
+    fn reference_is_the_left_operand() {
+        // Pasa si referencia < stencil, como glStencilFunc
+        let state = StencilState::new(CompareFunction::Less, 2);
+        assert!(state.test(3));
+        assert!(!state.test(2));
+        assert!(!state.test(1));
+    }
+
+    #[test]
+    fn read_mask_applies_to_both_operands() {
+        let state = StencilState::new(CompareFunction::Equal, 0b1010_0001).with_masks(0x0F, 0xFF);
+        assert!(state.test(0b0101_0001));
+        assert!(!state.test(0b0101_0010));
+    }
+
+    #[test]
+    fn write_mask_limits_the_modified_bits() {
+        let state = StencilState::write(0xFF).with_masks(0xFF, 0x0F);
+        let mut stencil = 0x30;
+        assert!(state.test_and_update(&mut stencil, true));
+        assert_eq!(stencil, 0x3F);
+    }
+
+    #[test]
+    fn increments_and_decrements_saturate_or_wrap() {
+        let state = StencilState::new(CompareFunction::Always, 0);
+        assert_eq!(state.update(StencilOp::Increment, 255), 255);
+        assert_eq!(state.update(StencilOp::IncrementWrap, 255), 0);
+        assert_eq!(state.update(StencilOp::Decrement, 0), 0);
+        assert_eq!(state.update(StencilOp::DecrementWrap, 0), 255);
+    }
+
+    #[test]
+    fn write_marks_visible_samples_only() {
+        let state = StencilState::write(1);
+        let (mut visible, mut hidden) = (0, 0);
+        assert!(state.test_and_update(&mut visible, true));
+        assert!(!state.test_and_update(&mut hidden, false));
+        assert_eq!((visible, hidden), (1, 0));
+    }
+
+    #[test]
+    fn lines_and_points_use_the_stencil() {
+        use crate::color::Color;
+        use crate::framebuffer::Framebuffer;
+        use crate::pipeline::RenderState;
+        use nalgebra_glm::Vec3;
+
+        let mut framebuffer = Framebuffer::new_with_samples(8, 4, 2);
+        framebuffer.set_background_color(Color::black());
+        framebuffer.clear();
+        // Solo la mitad izquierda queda marcada
+        for y in 0..4 {
+            for x in 0..4 {
+                let first = (y * 8 + x) * 2;
+                framebuffer.stencil_buffer[first..first + 2].fill(1);
+            }
+        }
+
+        let stencil = StencilState::new(CompareFunction::Equal, 1).with_ops(StencilOp::Keep, StencilOp::Keep, StencilOp::Increment);
+        let masked = RenderState { stencil, ..RenderState::opaque() };
+        framebuffer.line(Vec3::new(0.5, 1.5, 0.0), Vec3::new(7.5, 1.5, 0.0), Color::white(), false, false, &masked);
+        framebuffer.dot(Vec3::new(6.0, 3.0, 0.0), 2, Color::white(), false, &masked);
+        framebuffer.resolve();
+
+        let row = &framebuffer.buffer[8..16];
+        assert!(row[..4].iter().all(|&pixel| pixel == 0xFFFFFF));
+        assert!(row[4..].iter().all(|&pixel| pixel == 0x000000));
+        assert!(framebuffer.buffer[8 * 2 + 5..8 * 2 + 7].iter().all(|&pixel| pixel == 0x000000));
+        // Las muestras que pasaron aplicaron `pass`
+        assert_eq!(&framebuffer.stencil_buffer[8 * 2..8 * 2 + 2], &[2, 2]);
+        assert_eq!(framebuffer.stencil_buffer[8 * 2 + 8], 0);
+    }
+
+    #[test]
+    fn parses_compare_reference_ops_and_masks() {
+        assert_eq!(StencilState::parse("not-equal,1").unwrap(), StencilState::new(CompareFunction::NotEqual, 1));
+        assert_eq!(StencilState::parse("always,2,keep,keep,replace").unwrap(), StencilState::write(2));
+        let masked = StencilState::parse("equal,1,zero,invert,increment-wrap,1,254").unwrap();
+        assert_eq!(
+            masked,
+            StencilState::new(CompareFunction::Equal, 1)
+                .with_ops(StencilOp::Zero, StencilOp::Invert, StencilOp::IncrementWrap)
+                .with_masks(1, 254)
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_stencil_text() {
+        assert!(StencilState::parse("equal").is_err());
+        assert!(StencilState::parse("equal,1,keep").is_err());
+        assert!(StencilState::parse("sometimes,1").is_err());
+        assert!(StencilState::parse("equal,256").is_err());
+        assert!(StencilState::parse("equal,1,keep,keep,swap").is_err());
+    }
+}
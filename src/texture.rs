@@ -0,0 +1,441 @@
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use crate::color::Color;
+use crate::fragment::Fragment;
+
+// Anisotropía máxima aceptada (igual que el límite habitual de las GPU)
+pub const MAX_ANISOTROPY: u32 = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterMode {
+    // Texel más cercano: nítido, con bordes pixelados al magnificar
+    Nearest,
+    // Mezcla de los cuatro texels vecinos
+    Bilinear,
+    // Bilineal en los dos niveles de mipmap más cercanos al tamaño en pantalla
+    Trilinear,
+}
+
+impl FilterMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "nearest" => Some(FilterMode::Nearest),
+            "bilinear" | "linear" => Some(FilterMode::Bilinear),
+            "trilinear" => Some(FilterMode::Trilinear),
+            _ => None,
+        }
+    }
+}
+
+// Qué hacer con coordenadas UV fuera de [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WrapMode {
+    Repeat,
+    ClampToEdge,
+    MirroredRepeat,
+}
+
+impl WrapMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "repeat" => Some(WrapMode::Repeat),
+            "clamp" => Some(WrapMode::ClampToEdge),
+            "mirror" => Some(WrapMode::MirroredRepeat),
+            _ => None,
+        }
+    }
+
+    // Lleva un índice de texel arbitrario al rango [0, size)
+    fn apply(self, index: i64, size: usize) -> usize {
+        let size = size as i64;
+        let wrapped = match self {
+            WrapMode::Repeat => index.rem_euclid(size),
+            WrapMode::ClampToEdge => index.clamp(0, size - 1),
+            WrapMode::MirroredRepeat => {
+                let period = index.rem_euclid(2 * size);
+                if period >= size { 2 * size - 1 - period } else { period }
+            }
+        };
+        wrapped as usize
+    }
+}
+
+// Un nivel de la cadena de mipmaps
+pub struct MipLevel {
+    pub width: usize,
+    pub height: usize,
+    pub data: Vec<Color>,
+}
+
+impl MipLevel {
+    // Reduce a la mitad promediando bloques de 2x2. Con un tamaño impar la
+    // última columna (o fila) no tiene pareja y se suma al último texel de
+    // salida, que promedia 3 texels en ese eje; así no se pierde ningún texel.
+    fn downsample(&self) -> MipLevel {
+        let width = (self.width / 2).max(1);
+        let height = (self.height / 2).max(1);
+        let mut data = Vec::with_capacity(width * height);
+
+        // Texels de origen de la salida `index` en un eje de `size` texels
+        let span = |index: usize, out_size: usize, size: usize| {
+            let end = if index + 1 == out_size { size } else { index * 2 + 2 };
+            index * 2..end
+        };
+
+        for y in 0..height {
+            let rows = span(y, height, self.height);
+            for x in 0..width {
+                let columns = span(x, width, self.width);
+                let count = (rows.len() * columns.len()) as u32;
+                let mut sum = [0u32; 4];
+                for sy in rows.clone() {
+                    for texel in &self.data[sy * self.width + columns.start..sy * self.width + columns.end] {
+                        for (total, channel) in sum.iter_mut().zip([texel.r, texel.g, texel.b, texel.a]) {
+                            *total += channel as u32;
+                        }
+                    }
+                }
+                let [r, g, b, a] = sum.map(|total| ((total + count / 2) / count) as u8);
+                data.push(Color::rgba(r, g, b, a));
+            }
+        }
+
+        MipLevel { width, height, data }
+    }
+}
+
+// Imagen en memoria que los shaders muestrean con coordenadas UV. Al crearla se
+// genera la cadena completa de mipmaps hasta 1x1.
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    pub levels: Vec<MipLevel>,
+    pub filter: FilterMode,
+    pub wrap_u: WrapMode,
+    pub wrap_v: WrapMode,
+    // Muestras a lo largo del eje mayor de la huella del píxel (1 = isotrópico)
+    pub max_anisotropy: u32,
+}
+
+impl Texture {
+    pub fn new(width: usize, height: usize, data: Vec<Color>) -> Self {
+        assert_eq!(data.len(), width * height, "La textura no coincide con su tamaño");
+
+        let mut levels = vec![MipLevel { width, height, data }];
+        while let Some(last) = levels.last() {
+            if last.width == 1 && last.height == 1 {
+                break;
+            }
+            let next = last.downsample();
+            levels.push(next);
+        }
+
+        Texture {
+            width,
+            height,
+            levels,
+            filter: FilterMode::Trilinear,
+            wrap_u: WrapMode::Repeat,
+            wrap_v: WrapMode::Repeat,
+            max_anisotropy: 1,
+        }
+    }
+
+    // Tablero de ajedrez de `cells` x `cells` casillas, útil para revisar UVs
+    pub fn checkerboard(size: usize, cells: usize, a: Color, b: Color) -> Self {
+        let cell = (size / cells.max(1)).max(1);
+        let data = (0..size * size)
+            .map(|i| {
+                let (x, y) = (i % size, i / size);
+                if (x / cell + y / cell).is_multiple_of(2) { a } else { b }
+            })
+            .collect();
+        Texture::new(size, size, data)
+    }
+
+    // Normal map en espacio tangente (convención OpenGL) derivado de un mapa
+    // de alturas en [0, 1] por diferencias centrales
+    pub fn normal_map_from_heights(width: usize, height: usize, heights: &[f32], strength: f32) -> Self {
+        let at = |x: usize, y: usize| heights[y.min(height - 1) * width + x.min(width - 1)];
+        let data = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let dx = (at(x + 1, y) - at(x.saturating_sub(1), y)) * 0.5 * strength;
+                // Las filas crecen hacia abajo y v hacia arriba
+                let dy = (at(x, y.saturating_sub(1)) - at(x, y + 1)) * 0.5 * strength;
+                let normal = Vec3::new(-dx, -dy, 1.0).normalize();
+                let encode = |c: f32| ((c * 0.5 + 0.5) * 255.0).round() as u8;
+                Color::new(encode(normal.x), encode(normal.y), encode(normal.z))
+            })
+            .collect();
+        Texture::new(width, height, data)
+    }
+
+    // Paneles biselados de `cells` x `cells`, como normal map de ejemplo
+    pub fn panel_normal_map(size: usize, cells: usize) -> Self {
+        let cell = (size / cells.max(1)).max(1);
+        let bevel = (cell / 8).max(1) as f32;
+        let heights: Vec<f32> = (0..size * size)
+            .map(|i| {
+                let (x, y) = ((i % size) % cell, (i / size) % cell);
+                let edge = x.min(y).min(cell - 1 - x).min(cell - 1 - y) as f32;
+                (edge / bevel).min(1.0)
+            })
+            .collect();
+        Texture::normal_map_from_heights(size, size, &heights, 8.0)
+    }
+
+    // Carga PNG, JPEG, BMP o TGA; las imágenes sin canal alfa quedan opacas
+    pub fn load(path: &str) -> Result<Self, String> {
+        let image = image::open(path)
+            .map_err(|e| format!("Error al cargar la textura '{}': {}", path, e))?
+            .to_rgba8();
+        let (width, height) = image.dimensions();
+        let data = image
+            .pixels()
+            .map(|pixel| Color::rgba(pixel[0], pixel[1], pixel[2], pixel[3]))
+            .collect();
+
+        Ok(Texture::new(width as usize, height as usize, data))
+    }
+
+    pub fn with_filter(mut self, filter: FilterMode) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap_u = wrap;
+        self.wrap_v = wrap;
+        self
+    }
+
+    pub fn with_anisotropy(mut self, max_anisotropy: u32) -> Self {
+        self.max_anisotropy = max_anisotropy.clamp(1, MAX_ANISOTROPY);
+        self
+    }
+
+    // Texel del nivel `level` con el modo de repetición aplicado
+    fn texel(&self, level: &MipLevel, x: i64, y: i64) -> Vec4 {
+        let x = self.wrap_u.apply(x, level.width);
+        let y = self.wrap_v.apply(y, level.height);
+        let c = level.data[y * level.width + x];
+        Vec4::new(c.r as f32, c.g as f32, c.b as f32, c.a as f32)
+    }
+
+    // Muestrea el nivel base, sin mipmaps. v = 0 es la parte inferior de la
+    // imagen, como en los OBJ exportados de Blender.
+    pub fn sample(&self, uv: Vec2) -> Color {
+        self.sample_grad(uv, Vec2::zeros(), Vec2::zeros())
+    }
+
+    // Muestrea usando las derivadas de UV que calculó el rasterizador
+    pub fn sample_fragment(&self, fragment: &Fragment) -> Color {
+        self.sample_grad(fragment.tex_coords, fragment.tex_coords_dx, fragment.tex_coords_dy)
+    }
+
+    // Muestrea con las derivadas de UV respecto a x e y de pantalla. Con
+    // `Trilinear` eligen el nivel de mipmap y, si `max_anisotropy` > 1, se toman
+    // varias muestras a lo largo del eje mayor de la huella del píxel.
+    pub fn sample_grad(&self, uv: Vec2, ddx: Vec2, ddy: Vec2) -> Color {
+        let value = match self.filter {
+            FilterMode::Nearest => self.nearest(&self.levels[0], uv),
+            FilterMode::Bilinear => self.bilinear(&self.levels[0], uv),
+            FilterMode::Trilinear => self.trilinear_aniso(uv, ddx, ddy),
+        };
+        to_color(value)
+    }
+
+    fn nearest(&self, level: &MipLevel, uv: Vec2) -> Vec4 {
+        let x = (uv.x * level.width as f32).floor() as i64;
+        let y = ((1.0 - uv.y) * level.height as f32).floor() as i64;
+        self.texel(level, x, y)
+    }
+
+    fn bilinear(&self, level: &MipLevel, uv: Vec2) -> Vec4 {
+        // Los centros de texel están en (i + 0.5) / tamaño
+        let x = uv.x * level.width as f32 - 0.5;
+        let y = (1.0 - uv.y) * level.height as f32 - 0.5;
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+
+        let top = self.texel(level, x0, y0).lerp(&self.texel(level, x0 + 1, y0), tx);
+        let bottom = self.texel(level, x0, y0 + 1).lerp(&self.texel(level, x0 + 1, y0 + 1), tx);
+        top.lerp(&bottom, ty)
+    }
+
+    // Bilineal en los dos niveles que rodean a `lod`, mezclados linealmente
+    fn trilinear(&self, uv: Vec2, lod: f32) -> Vec4 {
+        let max_level = (self.levels.len() - 1) as f32;
+        let lod = lod.clamp(0.0, max_level);
+        let base = lod.floor() as usize;
+        let sample = self.bilinear(&self.levels[base], uv);
+
+        let t = lod - base as f32;
+        if t <= 0.0 || base + 1 >= self.levels.len() {
+            return sample;
+        }
+        sample.lerp(&self.bilinear(&self.levels[base + 1], uv), t)
+    }
+
+    fn trilinear_aniso(&self, uv: Vec2, ddx: Vec2, ddy: Vec2) -> Vec4 {
+        // Huella del píxel en texels del nivel base
+        let size = Vec2::new(self.width as f32, self.height as f32);
+        let length_x = ddx.component_mul(&size).magnitude();
+        let length_y = ddy.component_mul(&size).magnitude();
+
+        let (major, major_axis, minor) = if length_x >= length_y {
+            (length_x, ddx, length_y)
+        } else {
+            (length_y, ddy, length_x)
+        };
+
+        // Sin anisotropía el nivel sigue al eje mayor (más borroso pero sin
+        // aliasing); con ella se reparte el eje mayor entre varias muestras
+        let samples = if self.max_anisotropy > 1 && minor > 0.0 {
+            ((major / minor).ceil() as u32).clamp(1, self.max_anisotropy)
+        } else {
+            1
+        };
+
+        let lod = (major / samples as f32).max(f32::MIN_POSITIVE).log2();
+        if samples == 1 {
+            return self.trilinear(uv, lod);
+        }
+
+        let mut sum = Vec4::zeros();
+        for i in 0..samples {
+            let offset = (i as f32 + 0.5) / samples as f32 - 0.5;
+            sum += self.trilinear(uv + major_axis * offset, lod);
+        }
+        sum / samples as f32
+    }
+}
+
+fn to_color(value: Vec4) -> Color {
+    let channel = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+    Color::rgba(channel(value.x), channel(value.y), channel(value.z), channel(value.w))
+}
+
+impl Default for Texture {
+    // Tablero gris para revisar las UV cuando no hay imagen
+    fn default() -> Self {
+        Texture::checkerboard(256, 8, Color::new(230, 230, 230), Color::new(60, 60, 60))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Rampa horizontal de 4x1: rojo 0, 1, 2, 3
+    fn ramp() -> Texture {
+        Texture::new(4, 1, (0..4).map(|r| Color::new(r, 0, 0)).collect()).with_filter(FilterMode::Nearest)
+    }
+
+    fn red_at(texture: &Texture, u: f32) -> u8 {
+        texture.sample(Vec2::new(u, 0.5)).r
+    }
+
+    #[test]
+    fn wrap_modes_map_indices_into_range() {
+        let indices: Vec<usize> = (-5..9).map(|i| WrapMode::Repeat.apply(i, 4)).collect();
+        assert_eq!(indices, vec![3, 0, 1, 2, 3, 0, 1, 2, 3, 0, 1, 2, 3, 0]);
+
+        let indices: Vec<usize> = (-5..9).map(|i| WrapMode::ClampToEdge.apply(i, 4)).collect();
+        assert_eq!(indices, vec![0, 0, 0, 0, 0, 0, 1, 2, 3, 3, 3, 3, 3, 3]);
+
+        let indices: Vec<usize> = (-5..9).map(|i| WrapMode::MirroredRepeat.apply(i, 4)).collect();
+        assert_eq!(indices, vec![3, 3, 2, 1, 0, 0, 1, 2, 3, 3, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn sampling_outside_the_unit_square_uses_the_wrap_mode() {
+        let texture = ramp();
+        assert_eq!(red_at(&texture, 1.125), 0);
+        assert_eq!(red_at(&texture, -0.125), 3);
+
+        let texture = ramp().with_wrap(WrapMode::ClampToEdge);
+        assert_eq!(red_at(&texture, 1.125), 3);
+        assert_eq!(red_at(&texture, -0.125), 0);
+
+        let texture = ramp().with_wrap(WrapMode::MirroredRepeat);
+        assert_eq!(red_at(&texture, 1.125), 3);
+        assert_eq!(red_at(&texture, -0.125), 0);
+        assert_eq!(red_at(&texture, 1.625), 1);
+    }
+
+    #[test]
+    fn bilinear_blends_across_the_wrapped_edge() {
+        let texture = Texture::new(2, 1, vec![Color::new(0, 0, 0), Color::new(200, 0, 0)]);
+        // u = 0 cae entre el centro del último texel y el del primero
+        assert_eq!(texture.sample(Vec2::new(0.0, 0.5)).r, 100);
+        assert_eq!(texture.with_wrap(WrapMode::ClampToEdge).sample(Vec2::new(0.0, 0.5)).r, 0);
+    }
+
+    #[test]
+    fn wrap_and_filter_names_are_parsed() {
+        assert_eq!(WrapMode::from_name("Mirror"), Some(WrapMode::MirroredRepeat));
+        assert_eq!(WrapMode::from_name("clamp"), Some(WrapMode::ClampToEdge));
+        assert_eq!(WrapMode::from_name("tile"), None);
+        assert_eq!(FilterMode::from_name("linear"), Some(FilterMode::Bilinear));
+    }
+
+    fn level_sizes(texture: &Texture) -> Vec<(usize, usize)> {
+        texture.levels.iter().map(|level| (level.width, level.height)).collect()
+    }
+
+    // 8x8 con filas alternas blancas y negras, constante a lo largo de u
+    fn horizontal_stripes() -> Texture {
+        let data = (0..64).map(|i| if (i / 8) % 2 == 0 { Color::new(255, 255, 255) } else { Color::new(0, 0, 0) }).collect();
+        Texture::new(8, 8, data)
+    }
+
+    #[test]
+    fn mip_chain_halves_down_to_one_texel() {
+        assert_eq!(level_sizes(&Texture::checkerboard(8, 2, Color::black(), Color::black())), vec![(8, 8), (4, 4), (2, 2), (1, 1)]);
+        let texture = Texture::new(5, 3, vec![Color::black(); 15]);
+        assert_eq!(level_sizes(&texture), vec![(5, 3), (2, 1), (1, 1)]);
+        let texture = Texture::new(8, 2, vec![Color::black(); 16]);
+        assert_eq!(level_sizes(&texture), vec![(8, 2), (4, 1), (2, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn downsample_averages_two_by_two_blocks() {
+        let level = MipLevel {
+            width: 3,
+            height: 2,
+            data: vec![
+                Color::new(0, 10, 0), Color::new(100, 20, 0), Color::new(7, 0, 0),
+                Color::new(50, 30, 0), Color::new(51, 40, 255), Color::new(9, 0, 0),
+            ],
+        };
+        let smaller = level.downsample();
+        assert_eq!((smaller.width, smaller.height), (1, 1));
+        // Con ancho impar la última columna no se pierde: se promedian los seis
+        // texels, (0 + 100 + 7 + 50 + 51 + 9 + 3) / 6 en rojo
+        assert_eq!((smaller.data[0].r, smaller.data[0].g, smaller.data[0].b), (36, 17, 43));
+    }
+
+    #[test]
+    fn trilinear_selects_the_level_from_the_derivatives() {
+        let texture = horizontal_stripes();
+        let uv = Vec2::new(0.5, 0.9375);
+        // Un texel por píxel: nivel base, centro de la primera fila (blanca)
+        assert_eq!(texture.sample_grad(uv, Vec2::new(1.0 / 8.0, 0.0), Vec2::new(0.0, 1.0 / 8.0)).r, 255);
+        // Ocho texels por píxel: el nivel de 1x1 con el promedio
+        assert_eq!(texture.sample_grad(uv, Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0)).r, 128);
+        // A mitad de camino entre los niveles 0 y 1 se mezclan
+        let blended = texture.sample_grad(uv, Vec2::new(2.0f32.sqrt() / 8.0, 0.0), Vec2::zeros()).r;
+        assert!((180..=200).contains(&blended), "{}", blended);
+    }
+
+    #[test]
+    fn anisotropy_keeps_detail_along_the_minor_axis() {
+        // Huella de 8 texels en u y de uno en v
+        let (uv, ddx, ddy) = (Vec2::new(0.5, 0.9375), Vec2::new(1.0, 0.0), Vec2::new(0.0, 1.0 / 8.0));
+        assert_eq!(horizontal_stripes().sample_grad(uv, ddx, ddy).r, 128);
+        assert_eq!(horizontal_stripes().with_anisotropy(16).sample_grad(uv, ddx, ddy).r, 255);
+        assert_eq!(horizontal_stripes().with_anisotropy(64).max_anisotropy, MAX_ANISOTROPY);
+    }
+}
@@ -0,0 +1,106 @@
+use nalgebra_glm::Vec3;
+
+// Operador que lleva la radiancia HDR del framebuffer a [0, 1] antes de
+// codificarla en sRGB. `exposure` multiplica la radiancia antes del operador.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToneMapping {
+    // Solo recorta a [0, 1]: los colores de 8 bits se muestran tal cual
+    None,
+    // c / (1 + c): comprime los brillos sin saturar nunca a blanco
+    Reinhard,
+    // Ajuste de la curva fílmica ACES de Krzysztof Narkowicz, con más contraste
+    Aces,
+    // 1 - e^(-c): como una película con la exposición dada
+    Exposure,
+}
+
+impl ToneMapping {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(ToneMapping::None),
+            "reinhard" => Some(ToneMapping::Reinhard),
+            "aces" => Some(ToneMapping::Aces),
+            "exposure" => Some(ToneMapping::Exposure),
+            _ => None,
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            ToneMapping::None => ToneMapping::Reinhard,
+            ToneMapping::Reinhard => ToneMapping::Aces,
+            ToneMapping::Aces => ToneMapping::Exposure,
+            ToneMapping::Exposure => ToneMapping::None,
+        }
+    }
+
+    // Radiancia lineal -> color lineal en [0, 1]
+    pub fn apply(self, color: Vec3, exposure: f32) -> Vec3 {
+        let color = (color * exposure).map(|c| c.max(0.0));
+        let mapped = match self {
+            ToneMapping::None => color,
+            ToneMapping::Reinhard => color.map(|c| c / (1.0 + c)),
+            ToneMapping::Aces => color.map(|c| (c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)),
+            ToneMapping::Exposure => color.map(|c| 1.0 - (-c).exp()),
+        };
+        mapped.map(|c| c.clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(value: f32) -> Vec3 {
+        Vec3::repeat(value)
+    }
+
+    fn close(a: Vec3, b: Vec3) -> bool {
+        (a - b).abs().max() < 1e-4
+    }
+
+    #[test]
+    fn operators_match_their_curves() {
+        assert!(close(ToneMapping::None.apply(gray(0.25), 1.0), gray(0.25)));
+        assert!(close(ToneMapping::Reinhard.apply(gray(1.0), 1.0), gray(0.5)));
+        assert!(close(ToneMapping::Reinhard.apply(gray(3.0), 1.0), gray(0.75)));
+        assert!(close(ToneMapping::Exposure.apply(gray(1.0), 1.0), gray(1.0 - (-1.0f32).exp())));
+        // Valor de referencia del ajuste de Narkowicz para una radiancia de 1
+        assert!(close(ToneMapping::Aces.apply(gray(1.0), 1.0), gray(2.54 / 3.16)));
+        assert!(close(ToneMapping::Aces.apply(gray(0.0), 1.0), gray(0.0)));
+    }
+
+    #[test]
+    fn output_stays_in_unit_range_and_is_monotonic() {
+        for mapping in [ToneMapping::None, ToneMapping::Reinhard, ToneMapping::Aces, ToneMapping::Exposure] {
+            let mut previous = -1.0;
+            for i in 0..200 {
+                let value = mapping.apply(gray(i as f32 * 0.1), 1.0).x;
+                assert!((0.0..=1.0).contains(&value), "{:?}: {}", mapping, value);
+                assert!(value >= previous, "{:?} no es monótono en {}", mapping, i);
+                previous = value;
+            }
+            assert_eq!(mapping.apply(gray(-2.0), 1.0), gray(0.0));
+        }
+    }
+
+    #[test]
+    fn exposure_scales_before_the_operator() {
+        for mapping in [ToneMapping::None, ToneMapping::Reinhard, ToneMapping::Aces, ToneMapping::Exposure] {
+            assert!(close(mapping.apply(gray(0.5), 4.0), mapping.apply(gray(2.0), 1.0)));
+        }
+        // Sin operador la exposición alta satura a blanco
+        assert_eq!(ToneMapping::None.apply(Vec3::new(0.1, 0.5, 2.0), 2.0), Vec3::new(0.2, 1.0, 1.0));
+    }
+
+    #[test]
+    fn names_and_cycle() {
+        assert_eq!(ToneMapping::from_name("ACES"), Some(ToneMapping::Aces));
+        assert_eq!(ToneMapping::from_name("filmic"), None);
+        let mut mapping = ToneMapping::None;
+        for _ in 0..4 {
+            mapping = mapping.next();
+        }
+        assert_eq!(mapping, ToneMapping::None);
+    }
+}
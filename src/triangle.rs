@@ -1,8 +1,11 @@
-use crate::framebuffer::Framebuffer;
+use crate::framebuffer::{sample_pattern, Tile, MAX_SAMPLES};
 use crate::fragment::Fragment;
-use crate::vertex::Vertex;
-use crate::color::Color;
-use nalgebra_glm::Vec3;
+use crate::vertex::{Vertex, MAX_VARYINGS};
+use crate::pipeline::{PipelineState, RenderState};
+use nalgebra_glm::{Vec2, Vec4};
+
+const SUBPIXEL_BITS: u32 = 8;
+const SUBPIXEL_SCALE: i64 = 1 << SUBPIXEL_BITS;
 
 pub struct Triangle {
     pub v1: Vertex,
@@ -15,77 +18,377 @@ impl Triangle {
         Triangle { v1, v2, v3 }
     }
 
-    pub fn draw(&self, framebuffer: &mut Framebuffer) -> Vec<Fragment> {
-        let mut fragments = Vec::new();
+    // Vértices en pantalla en punto fijo con SUBPIXEL_BITS bits de subpíxel
+    fn fixed_points(&self) -> [Point; 3] {
+        [&self.v1, &self.v2, &self.v3].map(|v| {
+            let p = v.transformed_position;
+            Point {
+                x: (p.x * SUBPIXEL_SCALE as f32).round() as i64,
+                y: (p.y * SUBPIXEL_SCALE as f32).round() as i64,
+            }
+        })
+    }
 
-        let v1_pos = self.v1.transformed_position;
-        let v2_pos = self.v2.transformed_position;
-        let v3_pos = self.v3.transformed_position;
+    // Doble del área con signo en unidades de subpíxel; con y hacia abajo es
+    // positiva para triángulos en sentido horario en pantalla
+    pub fn signed_area(&self) -> f32 {
+        let [a, b, c] = self.fixed_points();
+        orient2d(a, b, c) as f32
+    }
 
-        let (x1, y1, z1) = (v1_pos.x as i32, v1_pos.y as i32, v1_pos.z);
-        let (x2, y2, z2) = (v2_pos.x as i32, v2_pos.y as i32, v2_pos.z);
-        let (x3, y3, z3) = (v3_pos.x as i32, v3_pos.y as i32, v3_pos.z);
+    // Triángulos degenerados o descartados por face culling
+    pub fn is_culled(&self, pipeline: &PipelineState) -> bool {
+        let area = self.signed_area();
+        area == 0.0 || pipeline.is_culled(area)
+    }
 
-        let min_x = x1.min(x2).min(x3).max(0);
-        let max_x = x1.max(x2).max(x3).min(framebuffer.width as i32 - 1);
-        let min_y = y1.min(y2).min(y3).max(0);
-        let max_y = y1.max(y2).max(y3).min(framebuffer.height as i32 - 1);
+    // Profundidad de vista (w de clip) del centroide; sirve para ordenar los
+    // triángulos transparentes de atrás hacia adelante
+    pub fn view_depth(&self) -> f32 {
+        (1.0 / self.v1.inv_w + 1.0 / self.v2.inv_w + 1.0 / self.v3.inv_w) / 3.0
+    }
 
-        let edge1 = v2_pos - v1_pos;
-        let edge2 = v3_pos - v1_pos;
-        let triangle_normal = nalgebra_glm::cross(&edge1, &edge2).normalize();
+    // Rectángulo de píxeles (inclusivo) que toca el triángulo. Es conservador para
+    // que incluya píxeles cuyo centro queda fuera pero alguna muestra MSAA no.
+    pub fn bounding_box(&self) -> (i32, i32, i32, i32) {
+        let [a, b, c] = self.fixed_points();
+        (
+            (a.x.min(b.x).min(c.x) >> SUBPIXEL_BITS) as i32,
+            (a.y.min(b.y).min(c.y) >> SUBPIXEL_BITS) as i32,
+            (a.x.max(b.x).max(c.x) >> SUBPIXEL_BITS) as i32,
+            (a.y.max(b.y).max(c.y) >> SUBPIXEL_BITS) as i32,
+        )
+    }
+
+    // Rasteriza el triángulo dentro de un tile con funciones de arista en punto
+    // fijo. La cobertura se evalúa en cada muestra del píxel (solo el centro sin
+    // MSAA) y la regla top-left hace que una muestra sobre una arista compartida
+    // se dibuje exactamente una vez. Cada muestra cubierta pasa primero el
+    // stencil test y el depth test; si alguna es visible, el fragment shader se ejecuta una sola vez por
+    // píxel (en el centro) y su color se mezcla en las muestras visibles según `state`.
+    // Con `color_write` desactivado solo se escriben la profundidad y el stencil, sin sombrear.
+    pub fn rasterize<S>(&self, tile: &mut Tile, state: &RenderState, mut shade: S)
+    where
+        S: FnMut(&Fragment) -> Vec4,
+    {
+        let [a, mut b, mut c] = self.fixed_points();
+        let mut area = orient2d(a, b, c);
+        if area == 0 {
+            return;
+        }
+
+        // Se normaliza a sentido horario; `swapped` indica que v2 y v3 cambiaron de lugar
+        let swapped = area < 0;
+        if swapped {
+            std::mem::swap(&mut b, &mut c);
+            area = -area;
+        }
+
+        let (box_min_x, box_min_y, box_max_x, box_max_y) = self.bounding_box();
+        let min_x = box_min_x.max(tile.x as i32);
+        let max_x = box_max_x.min((tile.x + tile.width) as i32 - 1);
+        let min_y = box_min_y.max(tile.y as i32);
+        let max_y = box_max_y.min((tile.y + tile.height) as i32 - 1);
+        if min_x > max_x || min_y > max_y {
+            return;
+        }
+
+        // Una arista opuesta a cada vértice: edges[0] -> a, edges[1] -> b, edges[2] -> c
+        let edges = [Edge::new(b, c), Edge::new(c, a), Edge::new(a, b)];
+
+        // Desplazamiento de cada función de arista desde el centro hasta cada muestra
+        let pattern = sample_pattern(tile.samples);
+        let mut sample_offsets = [[0i64; MAX_SAMPLES]; 3];
+        for (offsets, edge) in sample_offsets.iter_mut().zip(edges.iter()) {
+            for (offset, &(sx, sy)) in offsets.iter_mut().zip(pattern.iter()) {
+                *offset = edge.offset(sx as i64 * SUBPIXEL_SCALE / 16, sy as i64 * SUBPIXEL_SCALE / 16);
+            }
+        }
+
+        let origin = Point {
+            x: ((min_x as i64) << SUBPIXEL_BITS) + SUBPIXEL_SCALE / 2,
+            y: ((min_y as i64) << SUBPIXEL_BITS) + SUBPIXEL_SCALE / 2,
+        };
+        let mut row = edges.map(|edge| edge.evaluate(origin));
+
+        let inv_area = 1.0 / area as f32;
+        let weights = |e: [i64; 3]| {
+            let w1 = (e[0] - edges[0].bias) as f32 * inv_area;
+            let w2 = (e[1] - edges[1].bias) as f32 * inv_area;
+            let w3 = (e[2] - edges[2].bias) as f32 * inv_area;
+            if swapped { (w1, w3, w2) } else { (w1, w2, w3) }
+        };
+        let (z1, z2, z3) = (
+            self.v1.transformed_position.z,
+            self.v2.transformed_position.z,
+            self.v3.transformed_position.z,
+        );
 
         for y in min_y..=max_y {
+            let mut e = row;
+
             for x in min_x..=max_x {
-                let (w1, w2, w3) = barycentric_coordinates(
-                    x as f32, y as f32,
-                    x1 as f32, y1 as f32,
-                    x2 as f32, y2 as f32,
-                    x3 as f32, y3 as f32,
-                );
+                let (px, py) = (x as usize, y as usize);
+                let mut sample_depths = [0.0f32; MAX_SAMPLES];
+                let mut visible_mask = 0u8;
 
-                if w1 >= 0.0 && w2 >= 0.0 && w3 >= 0.0 {
+                for (sample, depth_slot) in sample_depths.iter_mut().enumerate().take(pattern.len()) {
+                    let es = [
+                        e[0] + sample_offsets[0][sample],
+                        e[1] + sample_offsets[1][sample],
+                        e[2] + sample_offsets[2][sample],
+                    ];
+                    if es[0] < 0 || es[1] < 0 || es[2] < 0 {
+                        continue;
+                    }
+
+                    // z ya pasó por la división de perspectiva, así que es lineal en pantalla
+                    let (w1, w2, w3) = weights(es);
                     let depth = z1 * w1 + z2 * w2 + z3 * w3;
-                    
-                    let normal = (self.v1.transformed_normal * w1 + 
-                                 self.v2.transformed_normal * w2 + 
-                                 self.v3.transformed_normal * w3).normalize();
-
-                    let intensity = 1.0;
-
-                    let fragment = Fragment::new_with_normal(
-                        x as f32,
-                        y as f32,
-                        framebuffer.current_color,
-                        depth,
-                        normal,
-                        intensity,
-                    );
-
-                    fragments.push(fragment);
+
+                    if tile.test(px, py, sample, depth, state) {
+                        *depth_slot = depth;
+                        visible_mask |= 1 << sample;
+                    }
+                }
+
+                if visible_mask != 0 && !state.color_write {
+                    // Sin escritura de color (shadow maps, máscaras de stencil) no hace falta sombrear
+                    tile.write_fragment(px, py, &sample_depths, visible_mask, Vec4::zeros(), state);
+                } else if visible_mask != 0 {
+                    // Con MSAA el centro puede quedar fuera del triángulo; los atributos
+                    // se extrapolan igual que en el sombreado por píxel de una GPU
+                    let (w1, w2, w3) = weights(e);
+                    let mut fragment = self.interpolate_fragment(x as f32, y as f32, w1, w2, w3);
+
+                    // Derivadas de UV como en una GPU: cada píxel se compara con su vecino
+                    // horizontal y vertical dentro del mismo quad de 2x2 (alineado a
+                    // coordenadas pares), aunque el vecino quede fuera del triángulo
+                    let dir_x: i64 = if x & 1 == 0 { 1 } else { -1 };
+                    let dir_y: i64 = if y & 1 == 0 { 1 } else { -1 };
+                    let neighbor_x = [0, 1, 2].map(|i| e[i] + edges[i].step_x * dir_x);
+                    let neighbor_y = [0, 1, 2].map(|i| e[i] + edges[i].step_y * dir_y);
+                    let (nx1, nx2, nx3) = weights(neighbor_x);
+                    let (ny1, ny2, ny3) = weights(neighbor_y);
+                    fragment.tex_coords_dx = (self.interpolate_tex_coords(nx1, nx2, nx3) - fragment.tex_coords) * dir_x as f32;
+                    fragment.tex_coords_dy = (self.interpolate_tex_coords(ny1, ny2, ny3) - fragment.tex_coords) * dir_y as f32;
+
+                    let color = shade(&fragment);
+                    tile.write_fragment(px, py, &sample_depths, visible_mask, color, state);
+                }
+
+                for (value, edge) in e.iter_mut().zip(edges.iter()) {
+                    *value += edge.step_x;
                 }
             }
+
+            for (value, edge) in row.iter_mut().zip(edges.iter()) {
+                *value += edge.step_y;
+            }
+        }
+    }
+
+    fn interpolate_fragment(&self, x: f32, y: f32, w1: f32, w2: f32, w3: f32) -> Fragment {
+        let (p1, p2, p3) = self.perspective_weights(w1, w2, w3);
+
+        let normal = (self.v1.transformed_normal * p1 +
+                     self.v2.transformed_normal * p2 +
+                     self.v3.transformed_normal * p3).normalize();
+
+        let tangent = self.v1.transformed_tangent * p1 +
+                      self.v2.transformed_tangent * p2 +
+                      self.v3.transformed_tangent * p3;
+
+        let tex_coords = self.v1.tex_coords * p1 +
+                         self.v2.tex_coords * p2 +
+                         self.v3.tex_coords * p3;
+
+        let world_position = self.v1.world_position * p1 +
+                             self.v2.world_position * p2 +
+                             self.v3.world_position * p3;
+
+        let mut varyings = [0.0; MAX_VARYINGS];
+        for (i, value) in varyings.iter_mut().enumerate() {
+            *value = self.v1.varyings[i] * p1 +
+                     self.v2.varyings[i] * p2 +
+                     self.v3.varyings[i] * p3;
+        }
+
+        let mut fragment = Fragment::new_with_normal(x, y, normal);
+        fragment.tex_coords = tex_coords;
+        fragment.tangent = tangent;
+        fragment.world_position = world_position;
+        fragment.varyings = varyings;
+        fragment
+    }
+
+    fn interpolate_tex_coords(&self, w1: f32, w2: f32, w3: f32) -> Vec2 {
+        let (p1, p2, p3) = self.perspective_weights(w1, w2, w3);
+        self.v1.tex_coords * p1 + self.v2.tex_coords * p2 + self.v3.tex_coords * p3
+    }
+
+    // Convierte baricéntricas de pantalla en pesos con corrección de perspectiva:
+    // los atributos se interpolan como a/w y se dividen por la interpolación de 1/w
+    fn perspective_weights(&self, w1: f32, w2: f32, w3: f32) -> (f32, f32, f32) {
+        let p1 = w1 * self.v1.inv_w;
+        let p2 = w2 * self.v2.inv_w;
+        let p3 = w3 * self.v3.inv_w;
+        let sum = p1 + p2 + p3;
+
+        if sum.abs() < f32::EPSILON {
+            return (w1, w2, w3);
         }
 
-        fragments
+        (p1 / sum, p2 / sum, p3 / sum)
     }
 }
 
-fn barycentric_coordinates(
-    px: f32, py: f32,
-    ax: f32, ay: f32,
-    bx: f32, by: f32,
-    cx: f32, cy: f32,
-) -> (f32, f32, f32) {
-    let denom = (by - cy) * (ax - cx) + (cx - bx) * (ay - cy);
+#[derive(Debug, Clone, Copy)]
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+// Doble del área con signo del triángulo abc
+fn orient2d(a: Point, b: Point, c: Point) -> i64 {
+    (b.x - a.x) * (c.y - a.y) - (b.y - a.y) * (c.x - a.x)
+}
+
+// Función de arista de a hacia b, evaluada de forma incremental por píxel
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    a: Point,
+    b: Point,
+    step_x: i64,
+    step_y: i64,
+    bias: i64,
+}
+
+impl Edge {
+    fn new(a: Point, b: Point) -> Self {
+        // Con y hacia abajo y triángulos en sentido horario, una arista es "top"
+        // si es horizontal y va hacia la derecha, y "left" si sube en pantalla.
+        // Las demás aristas excluyen los centros de píxel que caen justo sobre ellas.
+        let is_top = a.y == b.y && b.x > a.x;
+        let is_left = b.y < a.y;
+
+        Edge {
+            a,
+            b,
+            step_x: (a.y - b.y) * SUBPIXEL_SCALE,
+            step_y: (b.x - a.x) * SUBPIXEL_SCALE,
+            bias: if is_top || is_left { 0 } else { -1 },
+        }
+    }
+
+    fn evaluate(&self, p: Point) -> i64 {
+        orient2d(self.a, self.b, p) + self.bias
+    }
+
+    // Cambio de la función al desplazar el punto (dx, dy) en unidades de subpíxel
+    fn offset(&self, dx: i64, dy: i64) -> i64 {
+        (self.a.y - self.b.y) * dx + (self.b.x - self.a.x) * dy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framebuffer::Framebuffer;
+    use nalgebra_glm::Vec3;
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+
+    fn screen_vertex(x: f32, y: f32) -> Vertex {
+        Vertex { transformed_position: Vec3::new(x, y, 0.5), ..Vertex::default() }
+    }
 
-    if denom.abs() < 1e-10 {
-        return (-1.0, -1.0, -1.0);
+    // Dibuja cada triángulo por separado en un framebuffer limpio y suma las
+    // muestras que quedaron con profundidad escrita
+    fn coverage_counts(triangles: &[[(f32, f32); 3]], samples: usize) -> Vec<u32> {
+        let mut framebuffer = Framebuffer::new_with_samples(WIDTH, HEIGHT, samples);
+        let mut counts = vec![0; WIDTH * HEIGHT * samples];
+        for [a, b, c] in triangles {
+            let triangle = Triangle::new_from_vertices(screen_vertex(a.0, a.1), screen_vertex(b.0, b.1), screen_vertex(c.0, c.1));
+            framebuffer.clear();
+            for tile in framebuffer.tiles_mut(16).iter_mut() {
+                triangle.rasterize(tile, &RenderState::opaque(), |_| Vec4::zeros());
+            }
+            for (count, depth) in counts.iter_mut().zip(framebuffer.zbuffer.iter()) {
+                if depth.is_finite() {
+                    *count += 1;
+                }
+            }
+        }
+        counts
     }
 
-    let w1 = ((by - cy) * (px - cx) + (cx - bx) * (py - cy)) / denom;
-    let w2 = ((cy - ay) * (px - cx) + (ax - cx) * (py - cy)) / denom;
-    let w3 = 1.0 - w1 - w2;
+    fn assert_watertight(triangles: &[[(f32, f32); 3]]) {
+        for samples in [1, 2, 4, 8] {
+            for (index, &count) in coverage_counts(triangles, samples).iter().enumerate() {
+                let pixel = index / samples;
+                assert_eq!(
+                    count, 1,
+                    "{}x: muestra {} del píxel ({}, {}) dibujada {} veces",
+                    samples, index % samples, pixel % WIDTH, pixel / WIDTH, count
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn fan_covers_every_sample_exactly_once() {
+        // Abanico alrededor de un centro arbitrario que cubre toda la pantalla;
+        // los triángulos alternan el sentido de giro
+        let center = (31.37, 22.71);
+        let spokes = 23;
+        let outer: Vec<(f32, f32)> = (0..spokes)
+            .map(|i| {
+                let angle = i as f32 / spokes as f32 * std::f32::consts::TAU + 0.1;
+                (center.0 + 120.0 * angle.cos(), center.1 + 120.0 * angle.sin())
+            })
+            .collect();
+
+        let triangles: Vec<[(f32, f32); 3]> = (0..spokes)
+            .map(|i| {
+                let (a, b) = (outer[i], outer[(i + 1) % spokes]);
+                if i % 2 == 0 { [center, a, b] } else { [center, b, a] }
+            })
+            .collect();
 
-    (w1, w2, w3)
-}
\ No newline at end of file
+        assert_watertight(&triangles);
+    }
+
+    #[test]
+    fn grid_edges_through_pixel_centers_are_drawn_once() {
+        // Malla de cuadrados partidos en diagonal con los vértices sobre centros
+        // de píxel, para que las aristas compartidas pasen justo por las muestras
+        let step = 4.0;
+        let mut triangles = Vec::new();
+        for row in -1..(HEIGHT as i32 / 4 + 1) {
+            for column in -1..(WIDTH as i32 / 4 + 1) {
+                let (x0, y0) = (column as f32 * step + 0.5, row as f32 * step + 0.5);
+                let (x1, y1) = (x0 + step, y0 + step);
+                let (diagonal_a, diagonal_b) = if (row + column) % 2 == 0 { ((x0, y0), (x1, y1)) } else { ((x1, y0), (x0, y1)) };
+                let others = if (row + column) % 2 == 0 { [(x1, y0), (x0, y1)] } else { [(x0, y0), (x1, y1)] };
+                for other in others {
+                    triangles.push([diagonal_a, diagonal_b, other]);
+                }
+            }
+        }
+
+        assert_watertight(&triangles);
+    }
+
+    #[test]
+    fn edge_bias_follows_the_top_left_rule() {
+        let point = |x: i64, y: i64| Point { x: x << SUBPIXEL_BITS, y: y << SUBPIXEL_BITS };
+        // Horizontal hacia la derecha (top) y subiendo en pantalla (left)
+        assert_eq!(Edge::new(point(0, 0), point(4, 0)).bias, 0);
+        assert_eq!(Edge::new(point(0, 4), point(0, 0)).bias, 0);
+        // Horizontal hacia la izquierda y bajando en pantalla
+        assert_eq!(Edge::new(point(4, 0), point(0, 0)).bias, -1);
+        assert_eq!(Edge::new(point(0, 0), point(0, 4)).bias, -1);
+    }
+}
@@ -1,14 +1,23 @@
-use nalgebra_glm::{Vec2, Vec3};
-use crate::color::Color;
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+
+// Número de varyings libres que los shaders pueden pasar al rasterizador
+pub const MAX_VARYINGS: usize = 8;
 
 #[derive(Clone, Debug)]
 pub struct Vertex {
     pub position: Vec3,
     pub normal: Vec3,
+    // Tangente en xyz y signo de la bitangente en w (convención MikkTSpace)
+    pub tangent: Vec4,
     pub tex_coords: Vec2,
-    pub color: Color,
+    pub clip_position: Vec4,
+    pub inv_w: f32,
     pub transformed_position: Vec3,
     pub transformed_normal: Vec3,
+    pub transformed_tangent: Vec4,
+    // Posición en espacio de mundo (matriz de modelo aplicada) para la iluminación
+    pub world_position: Vec3,
+    pub varyings: [f32; MAX_VARYINGS],
 }
 
 impl Vertex {
@@ -16,27 +25,38 @@ impl Vertex {
         Vertex {
             position,
             normal,
+            tangent: Vec4::new(1.0, 0.0, 0.0, 1.0),
             tex_coords,
-            color: Color::black(),
+            clip_position: Vec4::new(position.x, position.y, position.z, 1.0),
+            inv_w: 1.0,
             transformed_position: position,
             transformed_normal: normal,
+            transformed_tangent: Vec4::new(1.0, 0.0, 0.0, 1.0),
+            world_position: position,
+            varyings: [0.0; MAX_VARYINGS],
         }
     }
 
-    pub fn new_with_color(position: Vec3, color: Color) -> Self {
-        Vertex {
-            position,
-            normal: Vec3::new(0.0, 0.0, 0.0),
-            tex_coords: Vec2::new(0.0, 0.0),
-            color,
-            transformed_position: Vec3::new(0.0, 0.0, 0.0),
-            transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+    // Interpola todos los atributos; se usa al recortar triángulos en clip space
+    pub fn lerp(&self, other: &Vertex, t: f32) -> Vertex {
+        let mut varyings = self.varyings;
+        for (value, target) in varyings.iter_mut().zip(other.varyings.iter()) {
+            *value += (target - *value) * t;
         }
-    }
 
-    pub fn set_transformed(&mut self, position: Vec3, normal: Vec3) {
-        self.transformed_position = position;
-        self.transformed_normal = normal;
+        Vertex {
+            position: self.position + (other.position - self.position) * t,
+            normal: self.normal + (other.normal - self.normal) * t,
+            tangent: self.tangent + (other.tangent - self.tangent) * t,
+            tex_coords: self.tex_coords + (other.tex_coords - self.tex_coords) * t,
+            clip_position: self.clip_position + (other.clip_position - self.clip_position) * t,
+            inv_w: self.inv_w + (other.inv_w - self.inv_w) * t,
+            transformed_position: self.transformed_position + (other.transformed_position - self.transformed_position) * t,
+            transformed_normal: self.transformed_normal + (other.transformed_normal - self.transformed_normal) * t,
+            transformed_tangent: self.transformed_tangent + (other.transformed_tangent - self.transformed_tangent) * t,
+            world_position: self.world_position + (other.world_position - self.world_position) * t,
+            varyings,
+        }
     }
 }
 
@@ -45,10 +65,15 @@ impl Default for Vertex {
         Vertex {
             position: Vec3::new(0.0, 0.0, 0.0),
             normal: Vec3::new(0.0, 1.0, 0.0),
+            tangent: Vec4::new(1.0, 0.0, 0.0, 1.0),
             tex_coords: Vec2::new(0.0, 0.0),
-            color: Color::black(),
+            clip_position: Vec4::new(0.0, 0.0, 0.0, 1.0),
+            inv_w: 1.0,
             transformed_position: Vec3::new(0.0, 0.0, 0.0),
             transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+            transformed_tangent: Vec4::new(1.0, 0.0, 0.0, 1.0),
+            world_position: Vec3::new(0.0, 0.0, 0.0),
+            varyings: [0.0; MAX_VARYINGS],
         }
     }
 }
\ No newline at end of file